@@ -1,10 +1,565 @@
-use crate::model::game::Game;
+use crate::model::game::{
+    ActionOutcome, Game, GameAction, GameActionRequest, GamePhase, TurnClock,
+};
+use crate::model::game_record::{GameEvent, GameRecord};
+use crate::model::poker::Suit;
+use crate::model::user::User;
+use crate::rules::follow::legal_follows;
+use crate::rules::team::CalledCard;
+use anyhow::{anyhow, Error};
+use enum_iterator::all;
 use futures_util::future::BoxFuture;
+use futures_util::FutureExt;
+use parking_lot::RwLock;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::select;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::time::{timeout_at, Instant};
 
+/// the longest wait for a disconnected player before checking again, reconnects are notified anyway
+const RECONNECT_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// drives a single hand from dealing to result, every phase change is broadcast by the game
 pub trait GameEngine {
-    fn run_game(&self, game: Game) -> BoxFuture<()>;
+    fn run_game(&self, game: Arc<RwLock<Game>>) -> BoxFuture<'_, ()>;
+}
+
+/// waits for players to act
+#[derive(Default)]
+pub struct GameEngineImpl {}
+
+/// every player acts automatically, useful to run a whole hand in tests
+#[derive(Default)]
+pub struct GameEngineTestImpl {}
+
+impl GameEngine for GameEngineImpl {
+    fn run_game(&self, game: Arc<RwLock<Game>>) -> BoxFuture<'_, ()> {
+        async move { GameDriver::new(game, false).run().await }.boxed()
+    }
+}
+
+impl GameEngine for GameEngineTestImpl {
+    fn run_game(&self, game: Arc<RwLock<Game>>) -> BoxFuture<'_, ()> {
+        async move { GameDriver::new(game, true).run().await }.boxed()
+    }
+}
+
+/// play a recorded hand again without waiting, the same events always lead to the same game
+/// players are stand-ins with only the recorded user ids
+pub fn replay_game(record: &GameRecord) -> Result<Game, Error> {
+    let users = record
+        .user_ids
+        .iter()
+        .map(|id| {
+            Arc::new(RwLock::new(User {
+                id: *id,
+                ..Default::default()
+            }))
+        })
+        .collect();
+    let mut game = Game::with_seed(users, record.configs.clone(), record.seed);
+    if let Some(shop) = record.shop.clone() {
+        game.open_shop(shop);
+    }
+    for recorded in record.events.iter() {
+        match recorded.event.clone() {
+            GameEvent::DealStarted { declarer } => {
+                game.declarer = declarer;
+                game.declarer_fixed = record.declarer_fixed;
+                game.deal();
+                game.enter_phase(GamePhase::DeclaringPrime);
+            }
+            GameEvent::Acted { seat, action } => {
+                game.apply_action(seat, action)?;
+            }
+            GameEvent::PrimeResolved => {
+                game.resolve_prime();
+                game.pick_up_bottom();
+                game.enter_phase(GamePhase::ExchangingBottom);
+            }
+            GameEvent::TimedOut { seat } => game.record_event(GameEvent::TimedOut { seat }),
+            event @ GameEvent::AutoPlayRejected { .. } => game.record_event(event),
+            GameEvent::ToolGranted { seat, tool } => game.grant_tool(seat, tool),
+            GameEvent::ShopClosed => game.close_shop(),
+            GameEvent::Scored => {
+                game.score();
+                game.enter_phase(GamePhase::Finished);
+            }
+        }
+    }
+    Ok(game)
+}
+
+struct GameDriver {
+    game: Arc<RwLock<Game>>,
+    action_recv: Option<UnboundedReceiver<GameActionRequest>>,
+    auto_play: bool,
+    /// reserve time left by seat
+    reserves: Vec<Duration>,
+    /// actions applied so far, a turn is over once one more is applied
+    applied_count: u64,
+}
+
+#[derive(PartialEq, Eq)]
+enum Waited {
+    Handled,
+    TimedOut,
+    Closed,
+}
+
+impl GameDriver {
+    fn new(game: Arc<RwLock<Game>>, auto_play: bool) -> Self {
+        let action_recv = game.write().take_action_recv();
+        Self {
+            game,
+            action_recv,
+            auto_play,
+            reserves: vec![],
+            applied_count: 0,
+        }
+    }
+
+    async fn run(mut self) {
+        loop {
+            let phase = self.game.read().phase;
+            match phase {
+                GamePhase::Shopping => {
+                    if !self.auto_play {
+                        let secs = self.game.read().shop().map_or(0, |s| s.configs().shop_secs);
+                        let deadline = Instant::now() + Duration::from_secs(secs);
+                        self.game.write().set_turn_clock(TurnClock {
+                            seat: None,
+                            deadline,
+                            reserve: Duration::ZERO,
+                            paused: false,
+                        });
+                        // ends early once everyone is done shopping
+                        loop {
+                            let everyone_left = self
+                                .game
+                                .read()
+                                .shop()
+                                .is_none_or(|shop| shop.everyone_left());
+                            if everyone_left
+                                || self.next_action(Some(deadline)).await != Waited::Handled
+                            {
+                                break;
+                            }
+                        }
+                    }
+                    self.game.write().close_shop();
+                }
+                GamePhase::Dealing => {
+                    self.game.write().start_deal();
+                    let interval = self
+                        .game
+                        .read()
+                        .configurable_rules()
+                        .time_configs
+                        .deal_interval_millis;
+                    loop {
+                        let dealt = self.game.write().deal_round();
+                        if !dealt {
+                            break;
+                        }
+                        self.game.read().notify_state_changed();
+                        if !self.auto_play {
+                            let deadline = Instant::now() + Duration::from_millis(interval);
+                            // players can declare while cards are still coming
+                            while self.next_action(Some(deadline)).await == Waited::Handled {}
+                        }
+                    }
+                    self.game.write().enter_phase(GamePhase::DeclaringPrime);
+                }
+                GamePhase::DeclaringPrime => {
+                    if !self.auto_play {
+                        let window = self
+                            .game
+                            .read()
+                            .configurable_rules()
+                            .time_configs
+                            .declare_prime_secs;
+                        let deadline = Instant::now() + Duration::from_secs(window);
+                        self.game.write().set_turn_clock(TurnClock {
+                            seat: None,
+                            deadline,
+                            reserve: Duration::ZERO,
+                            paused: false,
+                        });
+                        // declarations and overrides keep coming until the window expires
+                        while self.next_action(Some(deadline)).await == Waited::Handled {}
+                    }
+                    let mut game = self.game.write();
+                    game.resolve_prime();
+                    game.pick_up_bottom();
+                    game.enter_phase(GamePhase::ExchangingBottom);
+                }
+                GamePhase::ExchangingBottom | GamePhase::CallingPartner | GamePhase::Playing => {
+                    if !self.next_turn().await {
+                        // nobody can act anymore, the game is abandoned
+                        return;
+                    }
+                }
+                GamePhase::Scoring => {
+                    let mut game = self.game.write();
+                    game.score();
+                    game.enter_phase(GamePhase::Finished);
+                }
+                GamePhase::Finished => return,
+            }
+        }
+    }
+
+    /// wait for whoever should act, and act for them once their time and reserve are used up
+    /// returns false when no more actions can arrive
+    async fn next_turn(&mut self) -> bool {
+        if self.auto_play {
+            return self.act_automatically();
+        }
+        let (seat, limit) = {
+            let game = self.game.read();
+            let time_configs = &game.configurable_rules().time_configs;
+            let secs = match game.phase {
+                GamePhase::ExchangingBottom => time_configs.bury_bottom_secs,
+                GamePhase::CallingPartner => time_configs.call_partner_secs,
+                _ => time_configs.play_turn_secs,
+            };
+            if self.reserves.is_empty() {
+                let reserve = Duration::from_secs(time_configs.reserve_secs.unwrap_or(0));
+                self.reserves = vec![reserve; game.players().len()];
+            }
+            (game.acting_seat().unwrap(), Duration::from_secs(secs))
+        };
+        let mut reserve = self.reserves[seat];
+        let mut started = Instant::now();
+        self.start_turn_clock(seat, started + limit, reserve, false);
+        let applied_before = self.applied_count;
+        // actions of others are rejected and do not end the turn
+        while self.applied_count == applied_before {
+            match self.next_action(Some(started + limit + reserve)).await {
+                Waited::Handled => {}
+                Waited::TimedOut if self.waits_for_reconnect(seat) => {
+                    reserve = Duration::ZERO;
+                    self.start_turn_clock(seat, Instant::now(), reserve, true);
+                    let mut change_recv = self.game.read().subscribe_state_changes();
+                    while self.applied_count == applied_before && self.waits_for_reconnect(seat) {
+                        // woken by actions, and by state changes like the player coming back
+                        let check = Instant::now() + RECONNECT_CHECK_TIMEOUT;
+                        select! {
+                            waited = self.next_action(Some(check)) => {
+                                if waited == Waited::Closed {
+                                    return false;
+                                }
+                            }
+                            _ = change_recv.changed() => {}
+                        }
+                    }
+                    started = Instant::now();
+                    self.start_turn_clock(seat, started + limit, reserve, false);
+                }
+                Waited::TimedOut => {
+                    self.game.write().record_event(GameEvent::TimedOut { seat });
+                    if !self.act_automatically() {
+                        return false;
+                    }
+                }
+                Waited::Closed => return false,
+            }
+        }
+        let overtime = started.elapsed().saturating_sub(limit);
+        self.reserves[seat] = reserve.saturating_sub(overtime);
+        true
+    }
+
+    fn start_turn_clock(&self, seat: usize, deadline: Instant, reserve: Duration, paused: bool) {
+        self.game.write().set_turn_clock(TurnClock {
+            seat: Some(seat),
+            deadline,
+            reserve,
+            paused,
+        });
+    }
+
+    fn waits_for_reconnect(&self, seat: usize) -> bool {
+        let game = self.game.read();
+        let pause_on_disconnect = game.configurable_rules().time_configs.pause_on_disconnect;
+        pause_on_disconnect && !game.players()[seat].user.read().is_online()
+    }
+
+    /// false when not even a searched action is accepted, the hand cannot go on then
+    fn act_automatically(&mut self) -> bool {
+        let (user_id, action) = self.auto_action();
+        let Err(mut rejection) = self.apply(user_id, action) else {
+            return true;
+        };
+        if let Some((user_id, action)) = self.searched_action() {
+            match self.apply(user_id, action) {
+                Ok(_) => return true,
+                Err(e) => rejection = e,
+            }
+        }
+        let mut game = self.game.write();
+        if let Some(seat) = game.acting_seat() {
+            game.record_event(GameEvent::AutoPlayRejected {
+                seat,
+                reason: rejection.to_string(),
+            });
+        }
+        false
+    }
+
+    /// apply one action sent by a player
+    async fn next_action(&mut self, deadline: Option<Instant>) -> Waited {
+        let recv = match self.action_recv.as_mut() {
+            None => return Waited::Closed,
+            Some(recv) => recv,
+        };
+        let req = match deadline {
+            None => recv.recv().await,
+            Some(deadline) => match timeout_at(deadline, recv.recv()).await {
+                Err(_) => return Waited::TimedOut,
+                Ok(req) => req,
+            },
+        };
+        match req {
+            None => Waited::Closed,
+            Some(req) => {
+                let result = self.apply(req.user_id, req.action);
+                let _ = req.reply.send(result);
+                Waited::Handled
+            }
+        }
+    }
+
+    fn apply(&mut self, user_id: u32, action: GameAction) -> Result<ActionOutcome, Error> {
+        let mut game = self.game.write();
+        let seat = game
+            .seat_of(user_id)
+            .ok_or(anyhow!("user {} is not playing", user_id))?;
+        // using a tool does not end the turn
+        let ends_turn = !matches!(action, GameAction::UseTool(_));
+        let outcome = game.apply_action(seat, action)?;
+        game.notify_state_changed();
+        if ends_turn {
+            self.applied_count += 1;
+        }
+        Ok(outcome)
+    }
+
+    /// the simplest valid action of whoever should act now
+    fn auto_action(&self) -> (u32, GameAction) {
+        let game = self.game.read();
+        match game.phase {
+            GamePhase::ExchangingBottom => {
+                let declarer = &game.players()[game.declarer];
+                let buried = declarer.hand[..game.bottom_size].to_vec();
+                (declarer.user_id(), GameAction::BuryBottom(buried))
+            }
+            GamePhase::CallingPartner => {
+                // aces of the sub suits first, then kings and so on, then later copies of them
+                let deck_size = game.configurable_rules().basic_configs.deck_size as u32;
+                let ranks = [0].into_iter().chain((1..13).rev());
+                let called_cards = (1..=deck_size)
+                    .flat_map(|nth| ranks.clone().map(move |rank| (nth, rank)))
+                    .flat_map(|(nth, rank)| {
+                        all::<Suit>()
+                            .filter(|suit| Some(*suit) != game.prime_suit)
+                            .map(move |suit| CalledCard {
+                                intrinsic_id: suit as u32 * 13 + rank,
+                                nth,
+                            })
+                    })
+                    .take(game.called_card_count())
+                    .collect();
+                let declarer = &game.players()[game.declarer];
+                (declarer.user_id(), GameAction::CallPartner(called_cards))
+            }
+            _ => {
+                let player = &game.players()[game.cur_turn];
+                let lead = game.cur_trick().and_then(|t| t.lead()).cloned();
+                let rule = game.power_rule();
+                let played = match lead {
+                    None => vec![*player
+                        .hand
+                        .iter()
+                        .min_by_key(|c| rule.power(c.card()))
+                        .unwrap()],
+                    Some(lead) => legal_follows(&player.hand, &lead, &rule).minimal(&rule),
+                };
+                (player.user_id(), GameAction::PlayCards(played))
+            }
+        }
+    }
+
+    /// a follow found by trying every choice, only when the simplest one is rejected
+    fn searched_action(&self) -> Option<(u32, GameAction)> {
+        let game = self.game.read();
+        if game.phase != GamePhase::Playing {
+            return None;
+        }
+        let player = &game.players()[game.cur_turn];
+        let lead = game.cur_trick().and_then(|t| t.lead()).cloned()?;
+        let rule = game.power_rule();
+        let played = legal_follows(&player.hand, &lead, &rule).search(&rule)?;
+        Some((player.user_id(), GameAction::PlayCards(played)))
+    }
 }
 
-struct GameEngineImpl {}
+#[tokio::test]
+async fn test_run_game() {
+    use crate::model::configs::GameConfigurations;
+    use crate::model::game::test_users;
+    use crate::rules::scoring::points_of;
+    let mut configs = GameConfigurations::default();
+    configs.basic_configs.max_player_count = 4;
+    let game = Arc::new(RwLock::new(Game::new(test_users(4), configs)));
+    let mut phase_recv = game.read().subscribe_phase_changes();
+    GameEngineTestImpl::default().run_game(game.clone()).await;
+    let mut phases = vec![];
+    while let Ok(phase) = phase_recv.try_recv() {
+        phases.push(phase);
+    }
+    assert_eq!(
+        phases,
+        vec![
+            GamePhase::DeclaringPrime,
+            GamePhase::ExchangingBottom,
+            GamePhase::Playing,
+            GamePhase::Scoring,
+            GamePhase::Finished
+        ]
+    );
+    let game = game.read();
+    let in_tricks: u32 = game.result.as_ref().unwrap().points_by_seat.iter().sum();
+    let in_bottom = points_of(game.bottom(), &game.configurable_rules().score_configs);
+    // 4 decks, each has 4 suits of 5, 10 and K
+    assert_eq!(in_tricks + in_bottom, 4 * 4 * 25);
+}
 
-struct GameEngineTestImpl {}
+#[tokio::test]
+async fn test_run_game_with_called_card() {
+    use crate::model::configs::GameConfigurations;
+    use crate::model::game::test_users;
+    let mut configs = GameConfigurations::default();
+    configs.basic_configs.max_player_count = 6;
+    configs.play_configs.team_formation = crate::rules::team::TeamFormation::CalledCard;
+    let game = Arc::new(RwLock::new(Game::new(test_users(6), configs)));
+    let mut phase_recv = game.read().subscribe_phase_changes();
+    GameEngineTestImpl::default().run_game(game.clone()).await;
+    let mut phases = vec![];
+    while let Ok(phase) = phase_recv.try_recv() {
+        phases.push(phase);
+    }
+    assert!(phases.contains(&GamePhase::CallingPartner));
+    let game = game.read();
+    assert_eq!(game.teams.called_cards.len(), 2);
+    assert!(game.teams.partners.len() <= 2);
+    assert!(!game.teams.partners.contains(&game.declarer));
+}
+
+#[tokio::test]
+async fn test_called_card_on_large_table() {
+    use crate::model::configs::{GameConfigurations, MAX_PLAYER_COUNT};
+    use crate::model::game::test_users;
+    let mut configs = GameConfigurations::default();
+    configs.basic_configs.max_player_count = MAX_PLAYER_COUNT as u8;
+    configs.play_configs.team_formation = crate::rules::team::TeamFormation::CalledCard;
+    configs.validate().unwrap();
+    let users = test_users(MAX_PLAYER_COUNT as u32);
+    let game = Arc::new(RwLock::new(Game::new(users, configs)));
+    GameEngineTestImpl::default().run_game(game.clone()).await;
+    let game = game.read();
+    // more cards are called than there are aces of the sub suits
+    assert_eq!(game.teams.called_cards.len(), 5);
+    assert_eq!(game.phase, GamePhase::Finished);
+}
+
+#[tokio::test]
+async fn test_replay_game() {
+    use crate::model::configs::GameConfigurations;
+    use crate::model::game::test_users;
+    let mut configs = GameConfigurations::default();
+    configs.basic_configs.max_player_count = 6;
+    configs.play_configs.team_formation = crate::rules::team::TeamFormation::CalledCard;
+    let game = Arc::new(RwLock::new(Game::new(test_users(6), configs)));
+    GameEngineTestImpl::default().run_game(game.clone()).await;
+    let game = game.read();
+    let replayed = replay_game(game.record()).unwrap();
+    assert_eq!(replayed.phase, GamePhase::Finished);
+    let events = |game: &Game| -> Vec<GameEvent> {
+        game.record()
+            .events
+            .iter()
+            .map(|e| e.event.clone())
+            .collect()
+    };
+    assert_eq!(events(&replayed), events(&game));
+    assert_eq!(
+        replayed.result.as_ref().unwrap().defender_points,
+        game.result.as_ref().unwrap().defender_points
+    );
+    let json = serde_json::to_string(game.record()).unwrap();
+    let record: GameRecord = serde_json::from_str(&json).unwrap();
+    assert_eq!(record.events.len(), game.record().events.len());
+}
+
+#[tokio::test]
+async fn test_auto_play_on_timeout() {
+    use crate::model::configs::{GameConfigurations, TimeConfigurations};
+    use crate::model::game::test_users;
+    let mut configs = GameConfigurations::default();
+    configs.basic_configs.max_player_count = 4;
+    configs.time_configs = TimeConfigurations {
+        deal_interval_millis: 0,
+        declare_prime_secs: 0,
+        bury_bottom_secs: 0,
+        call_partner_secs: 0,
+        play_turn_secs: 0,
+        reserve_secs: None,
+        pause_on_disconnect: false,
+        spectator_delay_secs: None,
+    };
+    let game = Arc::new(RwLock::new(Game::new(test_users(4), configs)));
+    // nobody ever acts, every turn times out
+    GameEngineImpl::default().run_game(game.clone()).await;
+    let game = game.read();
+    assert_eq!(game.phase, GamePhase::Finished);
+    assert!(game.result.is_some());
+}
+
+#[tokio::test]
+async fn test_resume_on_reconnect() {
+    use crate::model::configs::GameConfigurations;
+    use crate::model::game::test_users;
+    let mut configs = GameConfigurations::default();
+    configs.basic_configs.max_player_count = 4;
+    configs.time_configs.deal_interval_millis = 0;
+    configs.time_configs.declare_prime_secs = 0;
+    configs.time_configs.bury_bottom_secs = 0;
+    configs.time_configs.reserve_secs = None;
+    configs.time_configs.pause_on_disconnect = true;
+    // test users are all offline
+    let game = Arc::new(RwLock::new(Game::new(test_users(4), configs)));
+    let engine_game = game.clone();
+    let engine = tokio::spawn(async move { GameEngineImpl::default().run_game(engine_game).await });
+    let mut change_recv = game.read().subscribe_state_changes();
+    while !game.read().turn_clock.as_ref().is_some_and(|c| c.paused) {
+        change_recv.changed().await.unwrap();
+    }
+    assert_eq!(game.read().phase, GamePhase::ExchangingBottom);
+    {
+        let game = game.read();
+        game.players()[game.declarer].user.write().connection_count = 1;
+        game.notify_state_changed();
+    }
+    // acted for right away, long before the check timeout
+    tokio::time::timeout(Duration::from_secs(1), async {
+        while game.read().phase == GamePhase::ExchangingBottom {
+            change_recv.changed().await.unwrap();
+        }
+    })
+    .await
+    .unwrap();
+    engine.abort();
+}
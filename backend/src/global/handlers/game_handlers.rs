@@ -0,0 +1,389 @@
+use crate::global::room_manager::room_manager;
+use crate::model::game::{
+    ActionOutcome, Game, GameAction, GamePhase, PlayRejection, PlayResult, TurnClock,
+};
+use crate::model::game_view::{Countdown, GameView};
+use crate::model::poker::CardCopy;
+use crate::model::room::{Room, RoomStatus};
+use crate::model::shop::ShopView;
+use crate::model::tool::{ToolOutcome, ToolUse};
+use crate::rules::team::CalledCard;
+use crate::transport::request::{RequestHandler, RequestType};
+use crate::transport::stream::StreamHandler;
+use anyhow::{anyhow, Error};
+use futures::Stream;
+use futures_util::future::BoxFuture;
+use futures_util::FutureExt;
+use parking_lot::RwLock;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::{sleep_until, Instant};
+use tokio::{select, spawn};
+
+pub struct PlayCardsHandler;
+
+/// played cards are rejected with a reason the client can render, instead of an error
+pub const PLAY_CARDS_REQ_TYPE: RequestType<Vec<CardCopy>, PlayResult> =
+    RequestType::new("PlayCards");
+
+impl RequestHandler<Vec<CardCopy>, PlayResult> for PlayCardsHandler {
+    fn handle(&self, uid: u32, req: Vec<CardCopy>) -> BoxFuture<'_, Result<PlayResult, Error>> {
+        async move {
+            let not_playing = PlayResult::Rejected(PlayRejection::NotPlaying);
+            let Ok(game) = room_manager().find_game_by_user_id(uid) else {
+                return Ok(not_playing);
+            };
+            let sent = {
+                let game = game.read();
+                game.seat_of(uid)
+                    .and_then(|_| game.send_action(uid, GameAction::PlayCards(req)).ok())
+            };
+            let Some(reply_recv) = sent else {
+                return Ok(not_playing);
+            };
+            let result = match reply_recv.await? {
+                // tools are only used through `UseTool`
+                Ok(ActionOutcome::Applied | ActionOutcome::ToolUsed(_)) => PlayResult::Played,
+                Ok(ActionOutcome::ThrowFailed(failed_throw)) => PlayResult::ThrowFailed {
+                    forced: failed_throw.forced,
+                    penalty: game
+                        .read()
+                        .configurable_rules()
+                        .play_configs
+                        .failed_throw_penalty,
+                },
+                Err(e) => PlayResult::Rejected(e.downcast::<PlayRejection>()?),
+            };
+            Ok(result)
+        }
+        .boxed()
+    }
+}
+
+/// send an action of the user to the engine of their game, and wait until it is applied or rejected
+async fn game_action(uid: u32, action: GameAction) -> Result<ActionOutcome, Error> {
+    let game = room_manager().find_game_by_user_id(uid)?;
+    let reply_recv = game.read().send_action(uid, action)?;
+    reply_recv.await?
+}
+
+pub struct DeclarePrimeHandler;
+
+/// declare or override the prime suit while cards are dealt and during the declaring window
+pub const DECLARE_PRIME_REQ_TYPE: RequestType<Vec<CardCopy>, ()> = RequestType::new("DeclarePrime");
+
+impl RequestHandler<Vec<CardCopy>, ()> for DeclarePrimeHandler {
+    fn handle(&self, uid: u32, req: Vec<CardCopy>) -> BoxFuture<'_, Result<(), Error>> {
+        async move {
+            game_action(uid, GameAction::DeclarePrime(req)).await?;
+            Ok(())
+        }
+        .boxed()
+    }
+}
+
+pub struct BuryBottomHandler;
+
+/// the declarer buries as many cards as the bottom had, any of the hand including the picked up bottom
+pub const BURY_BOTTOM_REQ_TYPE: RequestType<Vec<CardCopy>, ()> = RequestType::new("BuryBottom");
+
+impl RequestHandler<Vec<CardCopy>, ()> for BuryBottomHandler {
+    fn handle(&self, uid: u32, req: Vec<CardCopy>) -> BoxFuture<'_, Result<(), Error>> {
+        async move {
+            game_action(uid, GameAction::BuryBottom(req)).await?;
+            Ok(())
+        }
+        .boxed()
+    }
+}
+
+pub struct CallPartnerHandler;
+
+/// the declarer calls cards once the bottom is buried, only with `TeamFormation::CalledCard`
+pub const CALL_PARTNER_REQ_TYPE: RequestType<Vec<CalledCard>, ()> = RequestType::new("CallPartner");
+
+impl RequestHandler<Vec<CalledCard>, ()> for CallPartnerHandler {
+    fn handle(&self, uid: u32, req: Vec<CalledCard>) -> BoxFuture<'_, Result<(), Error>> {
+        async move {
+            game_action(uid, GameAction::CallPartner(req)).await?;
+            Ok(())
+        }
+        .boxed()
+    }
+}
+
+pub struct UseToolHandler;
+
+/// the leader of a trick uses one of their tools before leading
+pub const USE_TOOL_REQ_TYPE: RequestType<ToolUse, ToolOutcome> = RequestType::new("UseTool");
+
+impl RequestHandler<ToolUse, ToolOutcome> for UseToolHandler {
+    fn handle(&self, uid: u32, req: ToolUse) -> BoxFuture<'_, Result<ToolOutcome, Error>> {
+        async move {
+            match game_action(uid, GameAction::UseTool(req)).await? {
+                ActionOutcome::ToolUsed(outcome) => Ok(outcome),
+                outcome => Err(anyhow!("unexpected outcome {:?}", outcome)),
+            }
+        }
+        .boxed()
+    }
+}
+
+/// apply a shop action of the user, then reply with the shop as the user sees it
+async fn shop_action(uid: u32, action: Option<GameAction>) -> Result<ShopView, Error> {
+    if let Some(action) = action {
+        game_action(uid, action).await?;
+        room_manager().sync_shop(uid)?;
+    }
+    let game = room_manager().find_game_by_user_id(uid)?;
+    let game = game.read();
+    let seat = game.seat_of(uid).ok_or(anyhow!("user is not playing"))?;
+    let shop = game.shop().ok_or(anyhow!("no shop in this hand"))?;
+    Ok(shop.view_of(seat))
+}
+
+pub struct ShopInventoryHandler;
+
+pub const SHOP_INVENTORY_REQ_TYPE: RequestType<(), ShopView> = RequestType::new("ShopInventory");
+
+impl RequestHandler<(), ShopView> for ShopInventoryHandler {
+    fn handle(&self, uid: u32, _: ()) -> BoxFuture<'_, Result<ShopView, Error>> {
+        async move { shop_action(uid, None).await }.boxed()
+    }
+}
+
+pub struct BuyShopItemHandler;
+
+/// index of the item in the shop
+pub const BUY_SHOP_ITEM_REQ_TYPE: RequestType<usize, ShopView> = RequestType::new("BuyShopItem");
+
+impl RequestHandler<usize, ShopView> for BuyShopItemHandler {
+    fn handle(&self, uid: u32, req: usize) -> BoxFuture<'_, Result<ShopView, Error>> {
+        async move { shop_action(uid, Some(GameAction::Buy(req))).await }.boxed()
+    }
+}
+
+pub struct RerollShopHandler;
+
+pub const REROLL_SHOP_REQ_TYPE: RequestType<(), ShopView> = RequestType::new("RerollShop");
+
+impl RequestHandler<(), ShopView> for RerollShopHandler {
+    fn handle(&self, uid: u32, _: ()) -> BoxFuture<'_, Result<ShopView, Error>> {
+        async move { shop_action(uid, Some(GameAction::Reroll)).await }.boxed()
+    }
+}
+
+pub struct LeaveShopHandler;
+
+/// done shopping, dealing starts once everyone is
+pub const LEAVE_SHOP_REQ_TYPE: RequestType<(), ShopView> = RequestType::new("LeaveShop");
+
+impl RequestHandler<(), ShopView> for LeaveShopHandler {
+    fn handle(&self, uid: u32, _: ()) -> BoxFuture<'_, Result<ShopView, Error>> {
+        async move { shop_action(uid, Some(GameAction::LeaveShop)).await }.boxed()
+    }
+}
+
+pub struct DealtCardsStreamHandler;
+
+/// the hand dealt so far first, then every new card dealt to the user, ends once dealing is over
+pub const DEALT_CARDS_STREAM_TYPE: RequestType<(), Vec<CardCopy>> =
+    RequestType::new("DealtCardsStream");
+
+impl StreamHandler<(), Vec<CardCopy>> for DealtCardsStreamHandler {
+    fn handle(
+        &self,
+        uid: u32,
+        _: (),
+    ) -> BoxFuture<'_, Result<Pin<Box<dyn Stream<Item = Vec<CardCopy>> + Send + 'static>>, Error>>
+    {
+        async move {
+            let game = room_manager().find_game_by_user_id(uid)?;
+            let (send, recv) = futures_channel::mpsc::unbounded::<Vec<CardCopy>>();
+            // read the hand and subscribe at once so no card is missed or sent twice
+            let (seat, mut sent, dealt_recv) = {
+                let game = game.read();
+                let seat = game.seat_of(uid).ok_or(anyhow!("user is not playing"))?;
+                let hand = game.players()[seat].hand.clone();
+                let _ = send.unbounded_send(hand.clone());
+                (seat, hand, game.subscribe_dealt_cards())
+            };
+            if let Some(mut dealt_recv) = dealt_recv {
+                spawn(async move {
+                    loop {
+                        let cards = match dealt_recv.recv().await {
+                            Ok((dealt_seat, card)) if dealt_seat == seat => vec![card],
+                            Ok(_) => continue,
+                            // cards were missed, resync with what is in the hand now
+                            Err(RecvError::Lagged(_)) => game.read().players()[seat]
+                                .hand
+                                .iter()
+                                .filter(|c| !sent.contains(c))
+                                .cloned()
+                                .collect(),
+                            Err(RecvError::Closed) => break,
+                        };
+                        sent.extend(cards.iter().cloned());
+                        if !cards.is_empty() && send.unbounded_send(cards).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+            let stream: Pin<Box<dyn Stream<Item = Vec<CardCopy>> + Send + 'static>> =
+                Box::pin(recv);
+            Ok(stream)
+        }
+        .boxed()
+    }
+}
+
+pub struct GameStateStreamHandler;
+
+/// the game as the user is allowed to see it, sent again on every change and on to the next hands,
+/// ends once the match is over or the user left the room
+pub const GAME_STATE_STREAM_TYPE: RequestType<(), GameView> = RequestType::new("GameStateStream");
+
+/// the hand the user plays in the room, None once the match is over or the user left
+fn hand_of_match(uid: u32, room: &Arc<RwLock<Room>>) -> Option<(Arc<RwLock<Game>>, usize)> {
+    let in_room = room_manager()
+        .find_room_by_user_id(uid)
+        .is_some_and(|r| Arc::ptr_eq(&r, room));
+    let game = {
+        let room = room.read();
+        if !in_room || !matches!(room.status, RoomStatus::InGame) {
+            return None;
+        }
+        room.cur_game.clone()?
+    };
+    let seat = game.read().seat_of(uid)?;
+    Some((game, seat))
+}
+
+impl StreamHandler<(), GameView> for GameStateStreamHandler {
+    fn handle(
+        &self,
+        uid: u32,
+        _: (),
+    ) -> BoxFuture<'_, Result<Pin<Box<dyn Stream<Item = GameView> + Send + 'static>>, Error>> {
+        async move {
+            let room = room_manager()
+                .find_room_by_user_id(uid)
+                .ok_or(anyhow!("user not in room"))?;
+            let mut room_recv = room.read().detailed_info_change_watch.clone_recv();
+            let (mut game, mut seat) =
+                hand_of_match(uid, &room).ok_or(anyhow!("user is not playing"))?;
+            let mut change_recv = game.read().subscribe_state_changes();
+            let (send, recv) = futures_channel::mpsc::unbounded::<GameView>();
+            spawn(async move {
+                'views: loop {
+                    change_recv.borrow_and_update();
+                    if send.unbounded_send(game.read().view_of(seat)).is_err() {
+                        break;
+                    }
+                    loop {
+                        // changes of the hand first, so its last view is sent before the match ends
+                        select! {
+                            biased;
+                            changed = change_recv.changed() => match changed {
+                                Ok(()) => continue 'views,
+                                Err(_) => break 'views,
+                            },
+                            changed = room_recv.changed() => {
+                                if changed.is_err() {
+                                    break 'views;
+                                }
+                            }
+                        }
+                        match hand_of_match(uid, &room) {
+                            Some((next, next_seat)) if !Arc::ptr_eq(&next, &game) => {
+                                game = next;
+                                seat = next_seat;
+                                change_recv = game.read().subscribe_state_changes();
+                                continue 'views;
+                            }
+                            Some(_) => {}
+                            None => {
+                                if change_recv.has_changed().unwrap_or(false) {
+                                    let _ = send.unbounded_send(game.read().view_of(seat));
+                                }
+                                break 'views;
+                            }
+                        }
+                    }
+                }
+            });
+            let stream: Pin<Box<dyn Stream<Item = GameView> + Send + 'static>> = Box::pin(recv);
+            Ok(stream)
+        }
+        .boxed()
+    }
+}
+
+pub struct SpectatorGameStreamHandler;
+
+/// the public view of the game in the spectator's room, held back by the configured spectator delay,
+/// countdowns included, ends once the game is finished
+pub const SPECTATOR_GAME_STREAM_TYPE: RequestType<(), GameView> =
+    RequestType::new("SpectatorGameStream");
+
+impl StreamHandler<(), GameView> for SpectatorGameStreamHandler {
+    fn handle(
+        &self,
+        uid: u32,
+        _: (),
+    ) -> BoxFuture<'_, Result<Pin<Box<dyn Stream<Item = GameView> + Send + 'static>>, Error>> {
+        async move {
+            let game = room_manager().find_game_by_user_id(uid)?;
+            let (mut change_recv, delay) = {
+                let game = game.read();
+                let delay = game
+                    .configurable_rules()
+                    .time_configs
+                    .spectator_delay_secs
+                    .unwrap_or(0);
+                (game.subscribe_state_changes(), Duration::from_secs(delay))
+            };
+            let (delayed_send, mut delayed_recv) =
+                tokio::sync::mpsc::unbounded_channel::<(Instant, GameView, Option<TurnClock>)>();
+            let (send, recv) = futures_channel::mpsc::unbounded::<GameView>();
+            spawn(async move {
+                loop {
+                    change_recv.borrow_and_update();
+                    let (view, clock) = {
+                        let game = game.read();
+                        (game.public_view(), game.turn_clock.clone())
+                    };
+                    let finished = view.phase == GamePhase::Finished;
+                    if delayed_send.send((Instant::now(), view, clock)).is_err() || finished {
+                        break;
+                    }
+                    if change_recv.changed().await.is_err() {
+                        break;
+                    }
+                }
+            });
+            // views are released in order, each one `delay` after it was taken
+            spawn(async move {
+                while let Some((taken_at, mut view, clock)) = delayed_recv.recv().await {
+                    sleep_until(taken_at + delay).await;
+                    // counted from now, as if the clock had started `delay` later
+                    view.countdown = clock.map(|clock| {
+                        Countdown::from(&TurnClock {
+                            deadline: clock.deadline + delay,
+                            ..clock
+                        })
+                    });
+                    if send.unbounded_send(view).is_err() {
+                        break;
+                    }
+                }
+            });
+            let stream: Pin<Box<dyn Stream<Item = GameView> + Send + 'static>> = Box::pin(recv);
+            Ok(stream)
+        }
+        .boxed()
+    }
+}
@@ -1,2 +1,3 @@
+pub mod game_handlers;
 pub mod room_handlers;
 pub mod user_handlers;
@@ -1,6 +1,6 @@
 use crate::global::room_manager::room_manager;
 use crate::global::settings::system_settings;
-use crate::model::configs::GameConfigurations;
+use crate::model::configs::{ConfigErrors, ConfigResult, GameConfigurations};
 use crate::model::room::{RoomDetailedInfo, RoomSimpleInfo};
 use crate::transport::request::{RequestHandler, RequestType};
 use crate::transport::stream::StreamHandler;
@@ -75,13 +75,40 @@ impl RequestHandler<u32, ()> for EnterRoomHandler {
     }
 }
 
+pub struct SpectateRoomHandler;
+
+/// enter the room by id without taking a seat
+pub const SPECTATE_ROOM_REQ_TYPE: RequestType<u32, ()> = RequestType::new("SpectateRoom");
+
+impl RequestHandler<u32, ()> for SpectateRoomHandler {
+    fn handle(&self, uid: u32, req: u32) -> BoxFuture<'_, Result<(), Error>> {
+        async move { room_manager().spectate_room(uid, req) }.boxed()
+    }
+}
+
+pub struct TakeSeatHandler;
+
+/// a spectator takes an empty seat of the room
+pub const TAKE_SEAT_REQ_TYPE: RequestType<(), ()> = RequestType::new("TakeSeat");
+
+impl RequestHandler<(), ()> for TakeSeatHandler {
+    fn handle(&self, uid: u32, _: ()) -> BoxFuture<'_, Result<(), Error>> {
+        async move { room_manager().take_seat(uid) }.boxed()
+    }
+}
+
 pub struct ChangeGameConfigHandler;
 
-pub const CHANGE_GAME_CONFIG_REQ_TYPE: RequestType<GameConfigurations, ()> =
+/// lists every invalid field when rejected, see `GameConfigurations::validate`
+pub const CHANGE_GAME_CONFIG_REQ_TYPE: RequestType<GameConfigurations, ConfigResult> =
     RequestType::new("ChangeGameConfig");
 
-impl RequestHandler<GameConfigurations, ()> for ChangeGameConfigHandler {
-    fn handle(&self, uid: u32, req: GameConfigurations) -> BoxFuture<Result<(), Error>> {
+impl RequestHandler<GameConfigurations, ConfigResult> for ChangeGameConfigHandler {
+    fn handle(
+        &self,
+        uid: u32,
+        req: GameConfigurations,
+    ) -> BoxFuture<'_, Result<ConfigResult, Error>> {
         async move {
             let room = room_manager()
                 .find_room_by_user_id(uid)
@@ -90,13 +117,48 @@ impl RequestHandler<GameConfigurations, ()> for ChangeGameConfigHandler {
                 return Err(anyhow!("user is not owner"));
             }
             let room_id = room.read().id;
-            room_manager().update_game_configs_of_room(room_id, req)?;
-            Ok(())
+            match room_manager().update_game_configs_of_room(room_id, req) {
+                Ok(()) => Ok(ConfigResult::Changed),
+                Err(e) => Ok(ConfigResult::Invalid(e.downcast::<ConfigErrors>()?.0)),
+            }
         }
         .boxed()
     }
 }
 
+pub struct TogglePreparedHandler;
+
+/// returns whether the user is prepared now
+pub const TOGGLE_PREPARED_REQ_TYPE: RequestType<(), bool> = RequestType::new("TogglePrepared");
+
+impl RequestHandler<(), bool> for TogglePreparedHandler {
+    fn handle(&self, uid: u32, _: ()) -> BoxFuture<'_, Result<bool, Error>> {
+        async move { room_manager().toggle_prepared(uid) }.boxed()
+    }
+}
+
+pub struct StartGameHandler;
+
+/// an optional seed to deal the same cards again
+pub const START_GAME_REQ_TYPE: RequestType<Option<u64>, ()> = RequestType::new("StartGame");
+
+impl RequestHandler<Option<u64>, ()> for StartGameHandler {
+    fn handle(&self, uid: u32, req: Option<u64>) -> BoxFuture<'_, Result<(), Error>> {
+        async move { room_manager().start_game(uid, req) }.boxed()
+    }
+}
+
+pub struct ChooseRelicHandler;
+
+/// index of the relic among the ones offered to the user, only between hands of a run
+pub const CHOOSE_RELIC_REQ_TYPE: RequestType<usize, ()> = RequestType::new("ChooseRelic");
+
+impl RequestHandler<usize, ()> for ChooseRelicHandler {
+    fn handle(&self, uid: u32, req: usize) -> BoxFuture<'_, Result<(), Error>> {
+        async move { room_manager().choose_relic(uid, req) }.boxed()
+    }
+}
+
 pub struct RoomDetailedInfoStreamHandler;
 
 pub const ROOM_DETAILED_INFO_STREAM_TYPE: RequestType<(), RoomDetailedInfo> =
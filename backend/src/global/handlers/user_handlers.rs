@@ -1,7 +1,9 @@
+use crate::global::room_manager::room_manager;
 use crate::global::user_manager::user_manager;
+use crate::model::preset::ConfigPreset;
 use crate::model::user::User;
 use crate::transport::request::{RequestHandler, RequestType};
-use anyhow::Error;
+use anyhow::{anyhow, Error};
 use futures_util::future::BoxFuture;
 use futures_util::FutureExt;
 
@@ -45,3 +47,86 @@ impl RequestHandler<String, ()> for ChangeCurUserNameHandler {
         .boxed()
     }
 }
+
+pub struct ListPresetsHandler;
+
+/// built-in presets first, then the ones saved by the user
+pub const LIST_PRESETS_REQ_TYPE: RequestType<(), Vec<ConfigPreset>> =
+    RequestType::new("ListPresets");
+
+impl RequestHandler<(), Vec<ConfigPreset>> for ListPresetsHandler {
+    fn handle(&self, uid: u32, _: ()) -> BoxFuture<'_, Result<Vec<ConfigPreset>, Error>> {
+        async move {
+            let user = user_manager()
+                .get(uid)
+                .ok_or(Error::msg("User not found"))?;
+            let presets = user.read().presets();
+            Ok(presets)
+        }
+        .boxed()
+    }
+}
+
+pub struct SavePresetHandler;
+
+/// `built_in` of the request is ignored, invalid configurations are rejected
+pub const SAVE_PRESET_REQ_TYPE: RequestType<ConfigPreset, ()> = RequestType::new("SavePreset");
+
+impl RequestHandler<ConfigPreset, ()> for SavePresetHandler {
+    fn handle(&self, uid: u32, req: ConfigPreset) -> BoxFuture<'_, Result<(), Error>> {
+        async move {
+            let user = user_manager()
+                .get(uid)
+                .ok_or(Error::msg("User not found"))?;
+            let mut user_lock = user.write();
+            user_lock.save_preset(&req.name, req.configs)
+        }
+        .boxed()
+    }
+}
+
+pub struct DeletePresetHandler;
+
+pub const DELETE_PRESET_REQ_TYPE: RequestType<String, ()> = RequestType::new("DeletePreset");
+
+impl RequestHandler<String, ()> for DeletePresetHandler {
+    fn handle(&self, uid: u32, req: String) -> BoxFuture<'_, Result<(), Error>> {
+        async move {
+            let user = user_manager()
+                .get(uid)
+                .ok_or(Error::msg("User not found"))?;
+            let mut user_lock = user.write();
+            user_lock.delete_preset(&req)
+        }
+        .boxed()
+    }
+}
+
+pub struct ApplyPresetHandler;
+
+/// makes the preset preferred, the room owned by the user changes to it as well
+pub const APPLY_PRESET_REQ_TYPE: RequestType<String, ()> = RequestType::new("ApplyPreset");
+
+impl RequestHandler<String, ()> for ApplyPresetHandler {
+    fn handle(&self, uid: u32, req: String) -> BoxFuture<'_, Result<(), Error>> {
+        async move {
+            let user = user_manager()
+                .get(uid)
+                .ok_or(Error::msg("User not found"))?;
+            let preset = user
+                .read()
+                .find_preset(&req)
+                .ok_or(anyhow!("preset not found {}", req))?;
+            // a preset the owned room cannot take does not become preferred either
+            if let Some(room) = room_manager().find_room_by_user_id(uid) {
+                if room.read().owner().read().id == uid {
+                    let room_id = room.read().id;
+                    room_manager().update_game_configs_of_room(room_id, preset.configs)?;
+                }
+            }
+            user.write().apply_preset(&req)?;
+            Ok(())
+        }
+        .boxed()
+    }
+}
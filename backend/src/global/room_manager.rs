@@ -1,8 +1,11 @@
 use crate::data_structure::shared_map::GlobalMap;
+use crate::engine::{GameEngine, GameEngineImpl};
 use crate::global::settings::system_settings;
 use crate::global::user_manager::user_manager;
 use crate::model::configs::GameConfigurations;
-use crate::model::room::{Room, RoomSimpleInfo};
+use crate::model::game::{Game, GamePhase};
+use crate::model::game_match::Match;
+use crate::model::room::{Room, RoomSimpleInfo, RoomStatus};
 use crate::utils::{DebouncePolicy, WatcherWrapper};
 use anyhow::{anyhow, Error};
 use parking_lot::RwLock;
@@ -11,6 +14,7 @@ use std::ops::Deref;
 use std::sync::{Arc, OnceLock};
 use std::time::Duration;
 use tokio::spawn;
+use tokio::time::{timeout_at, Instant};
 
 pub fn room_manager() -> &'static RoomManager {
     static ROOM_MANAGER: OnceLock<RoomManager> = OnceLock::new();
@@ -43,13 +47,20 @@ impl RoomManager {
         if self.user_id_map.read().contains_key(&user_id) {
             return Err(anyhow!("User already in a room"));
         }
-        let room = Self::id_map().add_default();
+        let user = user_manager()
+            .get(user_id)
+            .ok_or(anyhow!("User not found"))?;
+        // rooms start with the preferred preset of the creator
+        let configs = user
+            .read()
+            .preferred_game_config
+            .clone()
+            .unwrap_or_default();
+        // a room never starts with a configuration that cannot be played
+        configs.validate()?;
+        let room = Self::id_map().add(Room::with_game_configs(configs));
         let room_id = room.read().id;
-        room.write().users.push(
-            user_manager()
-                .get(user_id)
-                .ok_or(anyhow!("User not found"))?,
-        );
+        room.write().users.push(user);
         self.user_id_map.write().insert(user_id, room.clone());
         let mut room_detail_changed_recv = room.read().detailed_info_change_watch.clone_recv();
         let timeout = system_settings().non_active_room_time;
@@ -68,6 +79,13 @@ impl RoomManager {
                         }
                     },
                     Err(_) => {
+                        // a running hand only changes its game, the room is not idle
+                        let in_game = Self::id_map()
+                            .get(room_id)
+                            .is_some_and(|room| matches!(room.read().status, RoomStatus::InGame));
+                        if in_game {
+                            continue;
+                        }
                         room_manager().remove_room(room_id);
                         break;
                     }
@@ -87,6 +105,10 @@ impl RoomManager {
         let room = Self::id_map()
             .get(room_id)
             .ok_or(anyhow!("Room not found {}", room_id))?;
+        // the running match has no seat for latecomers, they spectate and take a seat between hands
+        if matches!(room.read().status, RoomStatus::InGame) {
+            return Err(anyhow!("Game already started, spectate the room instead"));
+        }
         if room.read().users.len()
             == room.read().game_configs().basic_configs.max_player_count as usize
         {
@@ -104,6 +126,63 @@ impl RoomManager {
         Ok(())
     }
 
+    /// spectators never take a seat of the room unless they ask to
+    pub fn spectate_room(&self, user_id: u32, room_id: u32) -> Result<(), Error> {
+        if self.user_id_map.read().contains_key(&user_id) {
+            return Err(anyhow!("User already in a room"));
+        }
+        let room = Self::id_map()
+            .get(room_id)
+            .ok_or(anyhow!("Room not found {}", room_id))?;
+        let user = user_manager()
+            .get(user_id)
+            .ok_or(anyhow!("User not found {}", user_id))?;
+        user.write().prepared = false;
+        room.write().spectators.push(user);
+        self.user_id_map.write().insert(user_id, room.clone());
+        room.write().notify_detail_changed();
+        Ok(())
+    }
+
+    /// a spectator takes an empty seat, during a match only between hands
+    /// where they take over the seat of whoever left, with its level
+    pub fn take_seat(&self, user_id: u32) -> Result<(), Error> {
+        let room = self
+            .find_room_by_user_id(user_id)
+            .ok_or(anyhow!("User not in a room"))?;
+        let mut room = room.write();
+        let in_game = matches!(room.status, RoomStatus::InGame);
+        if in_game {
+            let between_hands = room.cur_game.as_ref().is_none_or(|game| {
+                matches!(game.read().phase, GamePhase::Scoring | GamePhase::Finished)
+            });
+            if !between_hands {
+                return Err(anyhow!("Can only take a seat between hands"));
+            }
+        }
+        let index = room
+            .spectators
+            .iter()
+            .position(|u| u.read().id == user_id)
+            .ok_or(anyhow!("User is not a spectator"))?;
+        if room.users.len() >= room.game_configs().basic_configs.max_player_count as usize {
+            return Err(anyhow!("Room is full"));
+        }
+        if let (true, Some(game_match)) = (in_game, room.cur_match.as_ref()) {
+            let seated: Vec<u32> = room.users.iter().map(|u| u.read().id).collect();
+            let user = room.spectators[index].clone();
+            game_match.write().take_over_seat(user, &seated)?;
+        }
+        let user = room.spectators.remove(index);
+        user.write().prepared = false;
+        room.users.push(user);
+        room.notify_detail_changed();
+        drop(room);
+        self.all_rooms_simple_info_change_watch
+            .send(self.all_rooms_simple_info());
+        Ok(())
+    }
+
     pub fn remove_user_from_room(&self, user_id: u32, room_id: u32) -> Result<(), Error> {
         if !self.user_id_map.read().contains_key(&user_id) {
             return Err(anyhow!("User not in a room"));
@@ -113,6 +192,7 @@ impl RoomManager {
             .ok_or(anyhow!("Room not found"))?;
         // TODO cannot remove when prepared, and in game
         room.write().users.retain(|u| u.read().id != user_id);
+        room.write().spectators.retain(|u| u.read().id != user_id);
         self.user_id_map.write().remove(&user_id);
         if room.read().users.is_empty() {
             // when last person leave, remove room (room must have at least one user)
@@ -128,6 +208,171 @@ impl RoomManager {
         self.user_id_map.read().get(&user_id).cloned()
     }
 
+    pub fn toggle_prepared(&self, user_id: u32) -> Result<bool, Error> {
+        let room = self
+            .find_room_by_user_id(user_id)
+            .ok_or(anyhow!("User not in a room"))?;
+        if !matches!(room.read().status, RoomStatus::Waiting) {
+            return Err(anyhow!("Game already started"));
+        }
+        if room
+            .read()
+            .spectators
+            .iter()
+            .any(|u| u.read().id == user_id)
+        {
+            return Err(anyhow!("Spectators do not prepare"));
+        }
+        let user = user_manager()
+            .get(user_id)
+            .ok_or(anyhow!("User not found {}", user_id))?;
+        let prepared = !user.read().prepared;
+        user.write().prepared = prepared;
+        room.write().notify_detail_changed();
+        Ok(prepared)
+    }
+
+    /// only the owner can start a match, once the room is full and everyone is prepared
+    /// hands are played one after another until the match is finished
+    /// the same seed always deals the same cards, useful to replay a match
+    pub fn start_game(&self, user_id: u32, seed: Option<u64>) -> Result<(), Error> {
+        let room = self
+            .find_room_by_user_id(user_id)
+            .ok_or(anyhow!("User not in a room"))?;
+        let (game_match, game) = {
+            let mut room = room.write();
+            if room.owner().read().id != user_id {
+                return Err(anyhow!("User is not owner"));
+            }
+            if !matches!(room.status, RoomStatus::Waiting) {
+                return Err(anyhow!("Game already started"));
+            }
+            let configs = room.game_configs().clone();
+            if room.users.len() != configs.basic_configs.max_player_count as usize {
+                return Err(anyhow!(
+                    "Need {} players to start",
+                    configs.basic_configs.max_player_count
+                ));
+            }
+            if room.users.iter().any(|u| !u.read().prepared) {
+                return Err(anyhow!("Not everyone is prepared"));
+            }
+            let game_match = Match::new(room.users.clone(), configs, seed);
+            let game = game_match.next_game();
+            let game_match = Arc::new(RwLock::new(game_match));
+            room.cur_match = Some(game_match.clone());
+            room.cur_game = Some(game.clone());
+            room.status = RoomStatus::InGame;
+            room.notify_detail_changed();
+            (game_match, game)
+        };
+        self.all_rooms_simple_info_change_watch
+            .send(self.all_rooms_simple_info());
+        spawn(async move {
+            let mut game = game;
+            loop {
+                GameEngineImpl::default().run_game(game.clone()).await;
+                let abandoned = game.read().result.is_none();
+                game_match.write().record(&game.read());
+                if abandoned || game_match.read().is_finished() {
+                    break;
+                }
+                let choose_relic_secs = game_match
+                    .read()
+                    .configs()
+                    .match_configs
+                    .run
+                    .as_ref()
+                    .map(|run| run.choose_relic_secs);
+                if let Some(secs) = choose_relic_secs {
+                    // subscribed before checking, so no choice is missed
+                    let mut choice_recv = game_match.read().subscribe_relic_choices();
+                    // offers are in the match summary of the room
+                    room.write().notify_detail_changed();
+                    let deadline = Instant::now() + Duration::from_secs(secs);
+                    let all_chosen = || {
+                        game_match
+                            .read()
+                            .run
+                            .as_ref()
+                            .is_none_or(|r| r.all_chosen())
+                    };
+                    while !all_chosen() {
+                        if !matches!(
+                            timeout_at(deadline, choice_recv.changed()).await,
+                            Ok(Ok(()))
+                        ) {
+                            break;
+                        }
+                    }
+                    if let Some(run) = game_match.write().run.as_mut() {
+                        run.choose_for_the_rest();
+                    }
+                }
+                // under the room lock, so seats taken between hands are in the next one
+                let mut room = room.write();
+                game = game_match.read().next_game();
+                room.cur_game = Some(game.clone());
+                room.notify_detail_changed();
+            }
+            let mut room = room.write();
+            room.status = RoomStatus::Waiting;
+            for user in room.users.iter() {
+                user.write().prepared = false;
+            }
+            room.notify_detail_changed();
+            drop(room);
+            room_manager()
+                .all_rooms_simple_info_change_watch
+                .send(room_manager().all_rooms_simple_info());
+        });
+        Ok(())
+    }
+
+    /// between hands of a run, the relic at `index` of the offers to the user's seat
+    pub fn choose_relic(&self, user_id: u32, index: usize) -> Result<(), Error> {
+        let room = self
+            .find_room_by_user_id(user_id)
+            .ok_or(anyhow!("User not in a room"))?;
+        let game_match = room
+            .read()
+            .cur_match
+            .clone()
+            .ok_or(anyhow!("No match in progress"))?;
+        if game_match.read().is_finished() {
+            return Err(anyhow!("Match already finished"));
+        }
+        game_match.write().choose_relic(user_id, index)?;
+        room.write().notify_detail_changed();
+        Ok(())
+    }
+
+    /// after a shop action, the run of the match keeps bought relics and coins left
+    pub fn sync_shop(&self, user_id: u32) -> Result<(), Error> {
+        let room = self
+            .find_room_by_user_id(user_id)
+            .ok_or(anyhow!("User not in a room"))?;
+        let (game_match, game) = {
+            let room = room.read();
+            (room.cur_match.clone(), room.cur_game.clone())
+        };
+        if let (Some(game_match), Some(game)) = (game_match, game) {
+            game_match.write().sync_shop(&game.read());
+            // relics are in the match summary of the room
+            room.write().notify_detail_changed();
+        }
+        Ok(())
+    }
+
+    pub fn find_game_by_user_id(&self, user_id: u32) -> Result<Arc<RwLock<Game>>, Error> {
+        self.find_room_by_user_id(user_id)
+            .ok_or(anyhow!("user not in room"))?
+            .read()
+            .cur_game
+            .clone()
+            .ok_or(anyhow!("no game in progress"))
+    }
+
     pub fn remove_room(&self, room_id: u32) {
         Self::id_map().remove_id(room_id);
         self.user_id_map
@@ -153,6 +398,8 @@ impl RoomManager {
         let room = Self::id_map()
             .get(room_id)
             .ok_or(anyhow!("Room not found {}", room_id))?;
+        // the room can never become smaller than the users seated in it
+        configs.validate_for_seated(room.read().users.len())?;
         let old = room.read().game_configs().basic_configs.max_player_count;
         let max_player_count_changed = old != configs.basic_configs.max_player_count;
         room.write().update_game_configs(configs);
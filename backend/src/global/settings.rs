@@ -7,6 +7,8 @@ pub struct SystemSettings {
     pub non_active_room_time: u64,
     /// even no rooms are changed, still notify client current all rooms info
     pub passive_notify_all_rooms_info_interval: u64,
+    /// how often to check that a client is still connected
+    pub connection_heartbeat_interval: u64,
 }
 
 impl Default for SystemSettings {
@@ -14,6 +16,7 @@ impl Default for SystemSettings {
         Self {
             non_active_room_time: 600 * 1000,
             passive_notify_all_rooms_info_interval: 10000,
+            connection_heartbeat_interval: 1000,
         }
     }
 }
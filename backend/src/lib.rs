@@ -5,24 +5,36 @@ pub mod ext;
 pub mod global;
 pub mod model;
 pub mod rsocket;
+pub mod rules;
 pub mod test_client;
 pub mod transport;
 pub mod utils;
 
+use crate::global::handlers::game_handlers::{
+    BuryBottomHandler, BuyShopItemHandler, CallPartnerHandler, DealtCardsStreamHandler,
+    DeclarePrimeHandler, GameStateStreamHandler, LeaveShopHandler, PlayCardsHandler,
+    RerollShopHandler, ShopInventoryHandler, SpectatorGameStreamHandler, UseToolHandler,
+    BURY_BOTTOM_REQ_TYPE, BUY_SHOP_ITEM_REQ_TYPE, CALL_PARTNER_REQ_TYPE, DEALT_CARDS_STREAM_TYPE,
+    DECLARE_PRIME_REQ_TYPE, GAME_STATE_STREAM_TYPE, LEAVE_SHOP_REQ_TYPE, PLAY_CARDS_REQ_TYPE,
+    REROLL_SHOP_REQ_TYPE, SHOP_INVENTORY_REQ_TYPE, SPECTATOR_GAME_STREAM_TYPE, USE_TOOL_REQ_TYPE,
+};
 use crate::global::handlers::room_handlers::{
-    AllRoomSimpleInfoStreamHandler, ChangeGameConfigHandler, CreateRoomHandler, EnterRoomHandler,
-    LeaveRoomHandler, ListRoomSimpleInfoHandler, RoomDetailedInfoStreamHandler,
-    ALL_ROOM_SIMPLE_INFO_STREAM_TYPE, CHANGE_GAME_CONFIG_REQ_TYPE, CREATE_ROOM_REQ_TYPE,
-    ENTER_ROOM_REQ_TYPE, LEAVE_ROOM_REQ_TYPE, LIST_ROOM_SIMPLE_INFO_REQ_TYPE,
-    ROOM_DETAILED_INFO_STREAM_TYPE,
+    AllRoomSimpleInfoStreamHandler, ChangeGameConfigHandler, ChooseRelicHandler, CreateRoomHandler,
+    EnterRoomHandler, LeaveRoomHandler, ListRoomSimpleInfoHandler, RoomDetailedInfoStreamHandler,
+    SpectateRoomHandler, StartGameHandler, TakeSeatHandler, TogglePreparedHandler,
+    ALL_ROOM_SIMPLE_INFO_STREAM_TYPE, CHANGE_GAME_CONFIG_REQ_TYPE, CHOOSE_RELIC_REQ_TYPE,
+    CREATE_ROOM_REQ_TYPE, ENTER_ROOM_REQ_TYPE, LEAVE_ROOM_REQ_TYPE, LIST_ROOM_SIMPLE_INFO_REQ_TYPE,
+    ROOM_DETAILED_INFO_STREAM_TYPE, SPECTATE_ROOM_REQ_TYPE, START_GAME_REQ_TYPE,
+    TAKE_SEAT_REQ_TYPE, TOGGLE_PREPARED_REQ_TYPE,
 };
 use crate::global::handlers::user_handlers::{
-    ChangeCurUserNameHandler, GetCurUserHandler, CHANGE_CUR_USER_NAME_REQ_TYPE,
-    GET_CUR_USER_REQ_TYPE,
+    ApplyPresetHandler, ChangeCurUserNameHandler, DeletePresetHandler, GetCurUserHandler,
+    ListPresetsHandler, SavePresetHandler, APPLY_PRESET_REQ_TYPE, CHANGE_CUR_USER_NAME_REQ_TYPE,
+    DELETE_PRESET_REQ_TYPE, GET_CUR_USER_REQ_TYPE, LIST_PRESETS_REQ_TYPE, SAVE_PRESET_REQ_TYPE,
 };
 use crate::global::rsocket_manager::rsocket_manager;
 use crate::model::user::User;
-use crate::rsocket::ServerRSocket;
+use crate::rsocket::{watch_connection, ServerRSocket};
 use futures::executor;
 use global::user_manager::user_manager;
 use parking_lot::RwLock;
@@ -71,8 +83,10 @@ pub async fn main_inner(
                 let id = inner.read().id;
                 id
             });
+            let client_rsocket: Arc<dyn RSocket> = Arc::from(client_rsocket);
+            watch_connection(user_id, client_rsocket.clone());
             Ok(Box::new(ServerRSocket {
-                client_rsocket: Arc::from(client_rsocket),
+                client_rsocket,
                 user_id,
             }))
         }))
@@ -99,6 +113,10 @@ fn init_global_handlers() {
     // users
     rsocket_manager().add_request_handler(GET_CUR_USER_REQ_TYPE, GetCurUserHandler);
     rsocket_manager().add_request_handler(CHANGE_CUR_USER_NAME_REQ_TYPE, ChangeCurUserNameHandler);
+    rsocket_manager().add_request_handler(LIST_PRESETS_REQ_TYPE, ListPresetsHandler);
+    rsocket_manager().add_request_handler(SAVE_PRESET_REQ_TYPE, SavePresetHandler);
+    rsocket_manager().add_request_handler(DELETE_PRESET_REQ_TYPE, DeletePresetHandler);
+    rsocket_manager().add_request_handler(APPLY_PRESET_REQ_TYPE, ApplyPresetHandler);
 
     // rooms
     rsocket_manager().add_request_handler(CREATE_ROOM_REQ_TYPE, CreateRoomHandler);
@@ -106,7 +124,12 @@ fn init_global_handlers() {
         .add_request_handler(LIST_ROOM_SIMPLE_INFO_REQ_TYPE, ListRoomSimpleInfoHandler);
     rsocket_manager().add_request_handler(LEAVE_ROOM_REQ_TYPE, LeaveRoomHandler);
     rsocket_manager().add_request_handler(ENTER_ROOM_REQ_TYPE, EnterRoomHandler);
+    rsocket_manager().add_request_handler(SPECTATE_ROOM_REQ_TYPE, SpectateRoomHandler);
+    rsocket_manager().add_request_handler(TAKE_SEAT_REQ_TYPE, TakeSeatHandler);
     rsocket_manager().add_request_handler(CHANGE_GAME_CONFIG_REQ_TYPE, ChangeGameConfigHandler);
+    rsocket_manager().add_request_handler(TOGGLE_PREPARED_REQ_TYPE, TogglePreparedHandler);
+    rsocket_manager().add_request_handler(START_GAME_REQ_TYPE, StartGameHandler);
+    rsocket_manager().add_request_handler(CHOOSE_RELIC_REQ_TYPE, ChooseRelicHandler);
     rsocket_manager().add_stream_handler(
         ALL_ROOM_SIMPLE_INFO_STREAM_TYPE,
         AllRoomSimpleInfoStreamHandler,
@@ -117,4 +140,16 @@ fn init_global_handlers() {
     );
 
     // games
+    rsocket_manager().add_request_handler(DECLARE_PRIME_REQ_TYPE, DeclarePrimeHandler);
+    rsocket_manager().add_request_handler(BURY_BOTTOM_REQ_TYPE, BuryBottomHandler);
+    rsocket_manager().add_request_handler(CALL_PARTNER_REQ_TYPE, CallPartnerHandler);
+    rsocket_manager().add_request_handler(PLAY_CARDS_REQ_TYPE, PlayCardsHandler);
+    rsocket_manager().add_request_handler(USE_TOOL_REQ_TYPE, UseToolHandler);
+    rsocket_manager().add_request_handler(SHOP_INVENTORY_REQ_TYPE, ShopInventoryHandler);
+    rsocket_manager().add_request_handler(BUY_SHOP_ITEM_REQ_TYPE, BuyShopItemHandler);
+    rsocket_manager().add_request_handler(REROLL_SHOP_REQ_TYPE, RerollShopHandler);
+    rsocket_manager().add_request_handler(LEAVE_SHOP_REQ_TYPE, LeaveShopHandler);
+    rsocket_manager().add_stream_handler(DEALT_CARDS_STREAM_TYPE, DealtCardsStreamHandler);
+    rsocket_manager().add_stream_handler(GAME_STATE_STREAM_TYPE, GameStateStreamHandler);
+    rsocket_manager().add_stream_handler(SPECTATOR_GAME_STREAM_TYPE, SpectatorGameStreamHandler);
 }
@@ -1,6 +1,47 @@
-use crate::model::poker::Suit;
+use crate::model::poker::{Card, Suit};
+use std::cmp::Ordering;
 
-fn score_of_numeric_number(num: u32) -> u32 {
+#[test]
+fn test_power_order() {
+    use crate::model::poker::{cards, CardCopy};
+    let rule = PowerRule::new(Some(Suit::SPADES), vec![2, 3, 5]);
+    let spade = |num: u32| CardCopy::of(0, Suit::SPADES, num).card();
+    let heart = |num: u32| CardCopy::of(0, Suit::HEARTS, num).card();
+    let club = |num: u32| CardCopy::of(0, Suit::CLUBS, num).card();
+    let red_joker = cards().by_id(53);
+    let black_joker = cards().by_id(52);
+    let descending = [
+        spade(5),
+        red_joker,
+        black_joker,
+        heart(5),
+        spade(3),
+        heart(3),
+        spade(2),
+        spade(1),
+        spade(13),
+        spade(4),
+    ];
+    for pair in descending.windows(2) {
+        assert_eq!(rule.compare(pair[0], pair[1]), Ordering::Greater);
+    }
+    assert!(rule.classify(heart(2)).is_prime());
+    assert_eq!(
+        rule.classify(heart(4)),
+        PrimeOrSub::of_sub_suit(Suit::HEARTS)
+    );
+    let led = rule.classify(heart(4));
+    assert_eq!(
+        rule.compare_in_trick(heart(4), club(1), led),
+        Ordering::Greater
+    );
+    assert_eq!(
+        rule.compare_in_trick(spade(4), heart(1), led),
+        Ordering::Greater
+    );
+}
+
+pub fn score_of_numeric_number(num: u32) -> u32 {
     if num % 5 == 0 {
         return num;
     }
@@ -10,10 +51,19 @@ fn score_of_numeric_number(num: u32) -> u32 {
     return 0;
 }
 
-fn raw_power_of_numeric_number(num: u32) -> i32 {
+pub fn raw_power_of_numeric_number(num: u32) -> i32 {
     return if num == 1 { 14 } else { num as i32 };
 }
 
+fn raw_power_of_card(card: &Card) -> i32 {
+    match card.numeric_card_num {
+        Some(num) => raw_power_of_numeric_number(num),
+        // black joker 15, red joker 16
+        None => 15 + (card.intrinsic_id as i32 - 52),
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct PrimeOrSub {
     pub prime_suit: Option<Suit>,
     sub_suit: Option<Suit>,
@@ -44,4 +94,80 @@ impl PrimeOrSub {
     pub fn is_sub(&self) -> bool {
         !self.is_prime()
     }
+
+    pub fn sub_suit(&self) -> Option<Suit> {
+        self.sub_suit
+    }
+}
+
+/// how cards are classified and ranked once the prime suit is known
+/// jokers and the always prime numbers are prime, so is every card of the prime suit
+#[derive(Debug, Clone)]
+pub struct PowerRule {
+    pub prime_suit: Option<Suit>,
+    pub always_prime_numbers: Vec<u32>,
+}
+
+impl PowerRule {
+    pub fn new(prime_suit: Option<Suit>, always_prime_numbers: Vec<u32>) -> Self {
+        Self {
+            prime_suit,
+            always_prime_numbers,
+        }
+    }
+
+    pub fn is_always_prime(&self, card: &Card) -> bool {
+        match card.numeric_card_num {
+            None => true,
+            Some(num) => self.always_prime_numbers.contains(&num),
+        }
+    }
+
+    pub fn is_prime_five(&self, card: &Card) -> bool {
+        card.numeric_card_num == Some(5) && card.suit.is_some() && card.suit == self.prime_suit
+    }
+
+    pub fn classify(&self, card: &Card) -> PrimeOrSub {
+        if self.is_always_prime(card) || card.suit == self.prime_suit {
+            return PrimeOrSub::of_prime_suit(self.prime_suit);
+        }
+        PrimeOrSub::of_sub_suit(card.suit.unwrap())
+    }
+
+    /// higher is stronger, only meaningful between cards of the same prime or sub suit
+    /// 5 of prime suit > jokers > always prime numbers > other prime cards > sub cards
+    pub fn power(&self, card: &Card) -> i32 {
+        if self.is_prime_five(card) {
+            return 10000000;
+        }
+        let mut pow = raw_power_of_card(card);
+        if self.is_always_prime(card) {
+            pow *= 1000;
+            if card.suit.is_some() && card.suit == self.prime_suit {
+                pow += 1;
+            }
+        } else if card.suit == self.prime_suit {
+            pow *= 100;
+        }
+        pow
+    }
+
+    pub fn compare(&self, a: &Card, b: &Card) -> Ordering {
+        self.power(a).cmp(&self.power(b))
+    }
+
+    /// power of a card played in a trick led by `led`,
+    /// cards neither prime nor of the led sub suit can never win and rank 0
+    pub fn power_in_trick(&self, card: &Card, led: PrimeOrSub) -> i32 {
+        let class = self.classify(card);
+        if class.is_prime() || class == led {
+            return self.power(card);
+        }
+        0
+    }
+
+    pub fn compare_in_trick(&self, a: &Card, b: &Card, led: PrimeOrSub) -> Ordering {
+        self.power_in_trick(a, led)
+            .cmp(&self.power_in_trick(b, led))
+    }
 }
@@ -1,4 +1,44 @@
+use crate::model::shoe::Shoe;
+use crate::model::tool::{ToolGrant, ToolKind};
+use crate::rules::team::TeamFormation;
 use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+
+#[test]
+fn test_validate() {
+    assert!(GameConfigurations::default().validate().is_ok());
+    let mut configs = GameConfigurations::default();
+    configs.basic_configs.max_player_count = 1;
+    configs.basic_configs.bottom_size = Some(10);
+    configs.score_configs.level_step = 0;
+    let fields: Vec<String> = configs
+        .validate()
+        .unwrap_err()
+        .0
+        .into_iter()
+        .map(|e| e.field)
+        .collect();
+    assert_eq!(
+        fields,
+        vec!["basic_configs.max_player_count", "score_configs.level_step"]
+    );
+    let mut configs = GameConfigurations::default();
+    // 216 - 10 cannot be split among 4 players
+    configs.basic_configs.bottom_size = Some(10);
+    configs.basic_configs.max_player_count = 4;
+    let errors = configs.validate_for_seated(6).unwrap_err().0;
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[0].field, "basic_configs.max_player_count");
+    assert_eq!(errors[1].field, "basic_configs.bottom_size");
+    configs.basic_configs.bottom_size = None;
+    configs.basic_configs.max_player_count = MAX_PLAYER_COUNT as u8 + 2;
+    let errors = configs.validate().unwrap_err().0;
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].field, "basic_configs.max_player_count");
+}
+
+/// the biggest table allowed, so there are always enough cards to deal and to call partners
+pub const MAX_PLAYER_COUNT: usize = 12;
 
 /// all configurable rules of how the game is played
 #[derive(Debug, Deserialize, Serialize, Clone, Default)]
@@ -7,11 +47,160 @@ pub struct GameConfigurations {
     pub play_configs: PlayConfigurations,
     pub time_configs: TimeConfigurations,
     pub score_configs: ScoreConfigurations,
+    pub match_configs: MatchConfigurations,
+}
+
+/// why a configuration cannot be played, `field` is its path like `basic_configs.deck_size`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+/// every invalid field at once, so the client can mark all of them
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConfigErrors(pub Vec<FieldError>);
+
+impl Display for ConfigErrors {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let errors: Vec<String> = self
+            .0
+            .iter()
+            .map(|e| format!("{}: {}", e.field, e.message))
+            .collect();
+        write!(f, "{}", errors.join("; "))
+    }
+}
+
+impl std::error::Error for ConfigErrors {}
+
+/// reply to a configuration change, invalid configurations change nothing
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConfigResult {
+    Changed,
+    Invalid(Vec<FieldError>),
+}
+
+impl GameConfigurations {
+    pub fn validate(&self) -> Result<(), ConfigErrors> {
+        self.validate_for_seated(0)
+    }
+
+    /// also checks that the `seated` users of a room still fit
+    pub fn validate_for_seated(&self, seated: usize) -> Result<(), ConfigErrors> {
+        let mut errors = vec![];
+        let mut check = |valid: bool, field: &str, message: String| {
+            if !valid {
+                errors.push(FieldError {
+                    field: field.to_string(),
+                    message,
+                });
+            }
+        };
+        let basic = &self.basic_configs;
+        let player_count = basic.max_player_count as usize;
+        check(
+            player_count >= 2,
+            "basic_configs.max_player_count",
+            "at least 2 players are needed".to_string(),
+        );
+        check(
+            player_count <= MAX_PLAYER_COUNT,
+            "basic_configs.max_player_count",
+            format!("at most {} players can play", MAX_PLAYER_COUNT),
+        );
+        check(
+            player_count >= seated,
+            "basic_configs.max_player_count",
+            format!("{} users are already seated", seated),
+        );
+        check(
+            player_count < 2
+                || player_count.is_multiple_of(2)
+                || self.play_configs.team_formation != TeamFormation::AlternatingSeats,
+            "play_configs.team_formation",
+            "alternating seats need an even number of players".to_string(),
+        );
+        check(
+            basic.deck_size >= 1,
+            "basic_configs.deck_size",
+            "at least 1 deck is needed".to_string(),
+        );
+        if player_count >= 2 && basic.deck_size >= 1 {
+            let shoe = Shoe::of_configs(basic);
+            check(
+                shoe.len() >= shoe.default_bottom_size(player_count) + player_count,
+                "basic_configs.deck_size",
+                format!("not enough cards to deal {} players", player_count),
+            );
+            if let Some(bottom_size) = basic.bottom_size {
+                check(
+                    shoe.is_valid_bottom_size(player_count, bottom_size),
+                    "basic_configs.bottom_size",
+                    "every player must be dealt the same number of cards".to_string(),
+                );
+            }
+        }
+        check(
+            self.play_configs
+                .always_prime_numbers
+                .iter()
+                .all(|n| (1..=13).contains(n)),
+            "play_configs.always_prime_numbers",
+            "numbers go from 1 to 13".to_string(),
+        );
+        let score = &self.score_configs;
+        let total_points =
+            basic.deck_size as u32 * 4 * (score.five_points + score.ten_points + score.king_points);
+        check(
+            score.defender_win_threshold >= 1 && score.defender_win_threshold <= total_points,
+            "score_configs.defender_win_threshold",
+            format!(
+                "must be between 1 and the {} points in the decks",
+                total_points
+            ),
+        );
+        check(
+            score.level_step >= 1,
+            "score_configs.level_step",
+            "must be at least 1".to_string(),
+        );
+        check(
+            score.max_level_change >= 1,
+            "score_configs.max_level_change",
+            "must be at least 1".to_string(),
+        );
+        let match_configs = &self.match_configs;
+        check(
+            match_configs.target_level >= 1,
+            "match_configs.target_level",
+            "must be at least 1".to_string(),
+        );
+        check(
+            match_configs.max_hands != Some(0),
+            "match_configs.max_hands",
+            "must be at least 1".to_string(),
+        );
+        if let Some(run) = match_configs.run.as_ref() {
+            check(
+                run.lives >= 1,
+                "match_configs.run.lives",
+                "must be at least 1".to_string(),
+            );
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigErrors(errors))
+        }
+    }
 }
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct BasicConfigurations {
     pub max_player_count: u8,
     pub deck_size: u8,
+    /// cards left as bottom after dealing, derived from deck size and player count when not set
+    pub bottom_size: Option<usize>,
 }
 
 impl Default for BasicConfigurations {
@@ -19,13 +208,172 @@ impl Default for BasicConfigurations {
         Self {
             max_player_count: 6,
             deck_size: 4,
+            bottom_size: None,
         }
     }
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone, Default)]
-pub struct PlayConfigurations {}
-#[derive(Debug, Deserialize, Serialize, Clone, Default)]
-pub struct TimeConfigurations {}
-#[derive(Debug, Deserialize, Serialize, Clone, Default)]
-pub struct ScoreConfigurations {}
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PlayConfigurations {
+    /// numbers that are prime no matter what suit they are
+    pub always_prime_numbers: Vec<u32>,
+    /// whether several combinations of the same prime or sub suit can be led at once
+    pub allow_throw: bool,
+    /// points lost by whoever throws combinations that others can beat
+    pub failed_throw_penalty: u32,
+    pub team_formation: TeamFormation,
+    /// whether players get and use tools at all
+    pub tools_enabled: bool,
+    /// remove a grant to keep its tool out of play
+    pub tool_grants: Vec<ToolGrant>,
+}
+
+impl Default for PlayConfigurations {
+    fn default() -> Self {
+        Self {
+            always_prime_numbers: vec![2, 3, 5],
+            allow_throw: true,
+            failed_throw_penalty: 10,
+            team_formation: TeamFormation::AlternatingSeats,
+            tools_enabled: false,
+            tool_grants: vec![
+                ToolGrant::EveryPlayer(ToolKind::ExchangeLocation),
+                ToolGrant::EveryPlayer(ToolKind::ReversePlayDirection),
+                ToolGrant::EveryPlayer(ToolKind::PeekBottomCard),
+            ],
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct TimeConfigurations {
+    /// pause between two rounds of dealing, each round gives every player one card
+    pub deal_interval_millis: u64,
+    /// how long players can still declare or override the prime suit after dealing
+    pub declare_prime_secs: u64,
+    pub bury_bottom_secs: u64,
+    pub call_partner_secs: u64,
+    /// for every play, the minimal legal play is made for whoever runs out of time
+    pub play_turn_secs: u64,
+    /// extra time of each player for the whole hand, used once a turn runs out
+    pub reserve_secs: Option<u64>,
+    /// wait for a disconnected player once their time is up, instead of playing for them
+    pub pause_on_disconnect: bool,
+    /// spectators see the game this much later, so they cannot help the players
+    pub spectator_delay_secs: Option<u64>,
+}
+
+impl Default for TimeConfigurations {
+    fn default() -> Self {
+        Self {
+            deal_interval_millis: 200,
+            declare_prime_secs: 15,
+            bury_bottom_secs: 60,
+            call_partner_secs: 30,
+            play_turn_secs: 20,
+            reserve_secs: Some(60),
+            pause_on_disconnect: false,
+            spectator_delay_secs: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ScoreConfigurations {
+    pub five_points: u32,
+    pub ten_points: u32,
+    pub king_points: u32,
+    /// bottom points are multiplied by this when defenders win the last trick
+    pub bottom_multiplier: u32,
+    /// defenders win the hand once they collect this many points
+    pub defender_win_threshold: u32,
+    /// every this many points away from the threshold is one more level
+    pub level_step: u32,
+    pub max_level_change: u32,
+}
+
+impl Default for ScoreConfigurations {
+    fn default() -> Self {
+        Self {
+            five_points: 5,
+            ten_points: 10,
+            king_points: 10,
+            bottom_multiplier: 2,
+            defender_win_threshold: 160,
+            level_step: 40,
+            max_level_change: 3,
+        }
+    }
+}
+
+/// a match is several hands in a row, keep these small for shorter matches
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct MatchConfigurations {
+    /// whoever reaches this level ends the match
+    pub target_level: u32,
+    /// the match ends after this many hands even if nobody reached the target
+    pub max_hands: Option<u32>,
+    /// play a rogue-like run instead, the target level is ignored then
+    pub run: Option<RunConfigurations>,
+}
+
+impl Default for MatchConfigurations {
+    fn default() -> Self {
+        Self {
+            target_level: 6,
+            max_hands: Some(8),
+            run: None,
+        }
+    }
+}
+
+/// a run goes on until a seat runs out of lives, relics are chosen between hands
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct RunConfigurations {
+    /// hands a seat can lose
+    pub lives: u32,
+    /// relics offered to each seat between hands
+    pub offer_count: usize,
+    pub choose_relic_secs: u64,
+    /// a shop opens before every hand but the first, None to play without
+    pub shop: Option<ShopConfigurations>,
+}
+
+impl Default for RunConfigurations {
+    fn default() -> Self {
+        Self {
+            lives: 3,
+            offer_count: 3,
+            choose_relic_secs: 15,
+            shop: Some(ShopConfigurations::default()),
+        }
+    }
+}
+
+/// economy of the shop, coins are earned in every hand of a run and kept until spent
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ShopConfigurations {
+    /// coins for every 10 points won in tricks
+    pub coins_per_ten_points: u32,
+    pub coins_per_win: u32,
+    pub tool_price: u32,
+    pub relic_price: u32,
+    pub reroll_price: u32,
+    /// items offered to each seat
+    pub item_count: usize,
+    pub shop_secs: u64,
+}
+
+impl Default for ShopConfigurations {
+    fn default() -> Self {
+        Self {
+            coins_per_ten_points: 1,
+            coins_per_win: 5,
+            tool_price: 3,
+            relic_price: 8,
+            reroll_price: 2,
+            item_count: 4,
+            shop_secs: 30,
+        }
+    }
+}
@@ -1,13 +1,899 @@
+use crate::model::baodatui_poker::PowerRule;
 use crate::model::configs::GameConfigurations;
+use crate::model::game_record::{GameEvent, GameRecord, RecordedEvent};
+use crate::model::poker::{CardCopy, Suit, DECK_CARD_COUNT};
+use crate::model::shoe::Shoe;
+use crate::model::shop::{Goods, Shop};
+use crate::model::tool::{in_tool_window, ToolGrant, ToolKind, ToolOutcome, ToolUse};
 use crate::model::user::User;
+use crate::rules::contains_all;
+use crate::rules::declare::PrimeDeclaration;
+use crate::rules::follow::{check_follow, check_lead, IllegalPlay};
+use crate::rules::scoring::{points_of, settle, Side};
+use crate::rules::team::{CalledCard, TeamFormation, Teams};
+use crate::rules::throw::Throw;
+use anyhow::{anyhow, Error};
+use parking_lot::RwLock;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::Duration;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::sync::{broadcast, oneshot, watch};
+use tokio::time::Instant;
+
+#[cfg(test)]
+pub(crate) fn test_users(count: u32) -> Vec<Arc<RwLock<User>>> {
+    (0..count)
+        .map(|id| {
+            Arc::new(RwLock::new(User {
+                id,
+                ..Default::default()
+            }))
+        })
+        .collect()
+}
+
+#[test]
+fn test_same_seed_same_deal() {
+    let configs = GameConfigurations::default();
+    let mut game1 = Game::with_seed(test_users(6), configs.clone(), 42);
+    let mut game2 = Game::with_seed(test_users(6), configs, 42);
+    game1.deal();
+    game2.deal();
+    for (p1, p2) in game1.players().iter().zip(game2.players()) {
+        assert_eq!(p1.hand, p2.hand);
+    }
+    assert_eq!(game1.bottom(), game2.bottom());
+}
+
+#[test]
+fn test_deal_rounds() {
+    let mut game = Game::with_seed(test_users(6), GameConfigurations::default(), 42);
+    game.start_deal();
+    let mut dealt_recv = game.subscribe_dealt_cards().unwrap();
+    assert!(game.deal_round());
+    assert!(game.players().iter().all(|p| p.hand.len() == 1));
+    let (seat, card) = dealt_recv.try_recv().unwrap();
+    assert_eq!((seat, card), (0, game.players()[0].hand[0]));
+    while game.deal_round() {}
+    assert!(game.subscribe_dealt_cards().is_none());
+    assert!(game.players().iter().all(|p| p.hand.len() == 35));
+}
+
+/// phases of a single hand, in the order the engine drives them
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum GamePhase {
+    /// only before hands of a run with a shop, everyone shops at the same time
+    Shopping,
+    Dealing,
+    DeclaringPrime,
+    ExchangingBottom,
+    /// only when partners are formed by called cards
+    CallingPartner,
+    Playing,
+    Scoring,
+    Finished,
+}
+
+/// everything a player can ask the running game to do
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum GameAction {
+    DeclarePrime(Vec<CardCopy>),
+    BuryBottom(Vec<CardCopy>),
+    CallPartner(Vec<CalledCard>),
+    PlayCards(Vec<CardCopy>),
+    UseTool(ToolUse),
+    /// index of the item in the shop
+    Buy(usize),
+    Reroll,
+    LeaveShop,
+}
+
+/// an action sent to the engine, the result is replied once it is applied or rejected
+pub struct GameActionRequest {
+    pub user_id: u32,
+    pub action: GameAction,
+    pub reply: oneshot::Sender<Result<ActionOutcome, Error>>,
+}
+
+/// how an accepted action was applied
+#[derive(Debug, Clone)]
+pub enum ActionOutcome {
+    Applied,
+    /// the throw was reduced to the forced cards
+    ThrowFailed(FailedThrow),
+    ToolUsed(ToolOutcome),
+}
+
+/// reply to a play, rejected plays change nothing
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum PlayResult {
+    Played,
+    /// others could beat part of the throw, only `forced` was played and `penalty` is lost
+    ThrowFailed {
+        forced: Vec<CardCopy>,
+        penalty: u32,
+    },
+    Rejected(PlayRejection),
+}
+
+/// why a play is rejected, the client renders these
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PlayRejection {
+    /// no running game, or the user is not one of its players
+    NotPlaying,
+    /// cards can only be played once the bottom is buried
+    NotPlayingPhase,
+    NotYourTurn,
+    Illegal(IllegalPlay),
+}
+
+impl From<IllegalPlay> for PlayRejection {
+    fn from(value: IllegalPlay) -> Self {
+        PlayRejection::Illegal(value)
+    }
+}
+
+impl Display for PlayRejection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlayRejection::NotPlaying => write!(f, "not playing"),
+            PlayRejection::NotPlayingPhase => write!(f, "cards cannot be played now"),
+            PlayRejection::NotYourTurn => write!(f, "not your turn"),
+            PlayRejection::Illegal(illegal) => write!(f, "{}", illegal),
+        }
+    }
+}
+
+impl std::error::Error for PlayRejection {}
 
 pub struct Game {
     players: Vec<Player>,
     configurable_rules: GameConfigurations,
+    /// the same seed and configurations always deal the same hands
+    pub seed: u64,
+    pub phase: GamePhase,
+    pub prime_suit: Option<Suit>,
+    /// the strongest declaration so far, its owner becomes the declarer unless `declarer_fixed`
+    pub declaration: Option<PrimeDeclaration>,
+    /// seat index of the player who owns the bottom cards and leads the first trick
+    pub declarer: usize,
+    /// set by the match after its first hand, declaring then only decides the prime suit
+    pub declarer_fixed: bool,
+    /// hidden from everybody but the declarer until the hand is scored
+    bottom: Vec<CardCopy>,
+    /// fixed once dealt, the declarer buries as many cards as picked up
+    pub bottom_size: usize,
+    pub tricks: Vec<Trick>,
+    pub cur_turn: usize,
+    /// seats in the order they play, changed by tools
+    pub turn_order: Vec<usize>,
+    pub failed_throws: Vec<FailedThrow>,
+    pub teams: Teams,
+    pub result: Option<GameResult>,
+    pub turn_clock: Option<TurnClock>,
+    record: GameRecord,
+    shop: Option<Shop>,
+    /// events are timed from here, set once dealing starts
+    started_at: Option<Instant>,
+    /// cards not dealt yet by seat, the next one to deal is last
+    undealt: Vec<Vec<CardCopy>>,
+    /// (seat, card) of every card dealt, only open while dealing
+    dealt_card_send: Option<broadcast::Sender<(usize, CardCopy)>>,
+    phase_change_send: broadcast::Sender<GamePhase>,
+    /// bumped on every change, views of the game are rebuilt from it
+    state_change_send: watch::Sender<u64>,
+    action_send: UnboundedSender<GameActionRequest>,
+    action_recv: Option<UnboundedReceiver<GameActionRequest>>,
 }
 
 pub struct Player {
-    user: Arc<RwLock<User>>,
+    pub user: Arc<RwLock<User>>,
+    pub hand: Vec<CardCopy>,
+    /// cards of all the tricks this player has won
+    pub won_cards: Vec<CardCopy>,
+    /// tools not used yet
+    pub tools: Vec<ToolKind>,
+    /// bottom cards this player has peeked at
+    pub peeked_bottom: Vec<CardCopy>,
+}
+
+impl Player {
+    pub fn new(user: Arc<RwLock<User>>) -> Self {
+        Self {
+            user,
+            hand: vec![],
+            won_cards: vec![],
+            tools: vec![],
+            peeked_bottom: vec![],
+        }
+    }
+
+    pub fn user_id(&self) -> u32 {
+        self.user.read().id
+    }
+
+    pub fn holds_all(&self, cards: &[CardCopy]) -> bool {
+        contains_all(&self.hand, cards)
+    }
+
+    fn remove_cards(&mut self, cards: &[CardCopy]) {
+        for card in cards {
+            if let Some(i) = self.hand.iter().position(|c| c == card) {
+                self.hand.remove(i);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Trick {
+    pub leader: usize,
+    /// (seat, cards) in play order
+    pub plays: Vec<(usize, Vec<CardCopy>)>,
+    pub winner: Option<usize>,
+    /// points won with this trick, set once it is finished
+    pub points: u32,
+}
+
+impl Trick {
+    fn new(leader: usize) -> Self {
+        Self {
+            leader,
+            plays: vec![],
+            winner: None,
+            points: 0,
+        }
+    }
+
+    pub fn lead(&self) -> Option<&Vec<CardCopy>> {
+        self.plays.first().map(|(_, cards)| cards)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GameResult {
+    /// points of the tricks won by each seat
+    pub points_by_seat: Vec<u32>,
+    /// points lost by each seat for failed throws
+    pub penalties_by_seat: Vec<u32>,
+    /// bottom points after the multiplier, only counted when defenders win the last trick
+    pub bottom_points: u32,
+    /// all points of the defender side, bottom and penalties included
+    pub defender_points: u32,
+    pub winner: Side,
+    pub level_change: u32,
+}
+
+/// who the game is waiting for and until when
+#[derive(Debug, Clone)]
+pub struct TurnClock {
+    /// None when anybody can act, like while declaring the prime suit
+    pub seat: Option<usize>,
+    pub deadline: Instant,
+    /// extra time once `deadline` is passed
+    pub reserve: Duration,
+    /// waiting for the player to reconnect, the turn starts over once they are back
+    pub paused: bool,
+}
+
+/// a throw that others could beat, only `forced` was actually played
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FailedThrow {
+    pub seat: usize,
+    pub attempted: Vec<CardCopy>,
+    pub forced: Vec<CardCopy>,
+}
+
+impl Game {
+    pub fn new(users: Vec<Arc<RwLock<User>>>, configurable_rules: GameConfigurations) -> Self {
+        Self::with_seed(users, configurable_rules, rand::thread_rng().gen())
+    }
+
+    pub fn with_seed(
+        users: Vec<Arc<RwLock<User>>>,
+        configurable_rules: GameConfigurations,
+        seed: u64,
+    ) -> Self {
+        let (phase_change_send, _) = broadcast::channel(16);
+        let (state_change_send, _) = watch::channel(0);
+        let (action_send, action_recv) = unbounded_channel();
+        // a whole shoe fits, even when it is dealt without pause
+        let shoe_len = Shoe::of_configs(&configurable_rules.basic_configs).len();
+        let (dealt_card_send, _) = broadcast::channel(shoe_len.max(1));
+        let user_ids = users.iter().map(|u| u.read().id).collect();
+        let turn_order = (0..users.len()).collect();
+        let record = GameRecord::new(seed, configurable_rules.clone(), user_ids);
+        Self {
+            players: users.into_iter().map(Player::new).collect(),
+            configurable_rules,
+            seed,
+            phase: GamePhase::Dealing,
+            prime_suit: None,
+            declaration: None,
+            declarer: 0,
+            declarer_fixed: false,
+            bottom: vec![],
+            bottom_size: 0,
+            tricks: vec![],
+            cur_turn: 0,
+            turn_order,
+            failed_throws: vec![],
+            teams: Teams::default(),
+            result: None,
+            turn_clock: None,
+            record,
+            shop: None,
+            started_at: None,
+            undealt: vec![],
+            dealt_card_send: Some(dealt_card_send),
+            phase_change_send,
+            state_change_send,
+            action_send,
+            action_recv: Some(action_recv),
+        }
+    }
+
+    pub fn players(&self) -> &Vec<Player> {
+        &self.players
+    }
+
+    pub fn configurable_rules(&self) -> &GameConfigurations {
+        &self.configurable_rules
+    }
+
+    pub fn seat_of(&self, user_id: u32) -> Option<usize> {
+        self.players.iter().position(|p| p.user_id() == user_id)
+    }
+
+    pub fn subscribe_phase_changes(&self) -> broadcast::Receiver<GamePhase> {
+        self.phase_change_send.subscribe()
+    }
+
+    pub fn subscribe_state_changes(&self) -> watch::Receiver<u64> {
+        self.state_change_send.subscribe()
+    }
+
+    pub fn notify_state_changed(&self) {
+        self.state_change_send.send_modify(|version| *version += 1);
+    }
+
+    /// can only be taken once, by the engine running this game
+    pub fn take_action_recv(&mut self) -> Option<UnboundedReceiver<GameActionRequest>> {
+        self.action_recv.take()
+    }
+
+    /// queue an action for the engine, the returned receiver resolves when it is handled
+    pub fn send_action(
+        &self,
+        user_id: u32,
+        action: GameAction,
+    ) -> Result<oneshot::Receiver<Result<ActionOutcome, Error>>, Error> {
+        let (reply, reply_recv) = oneshot::channel();
+        self.action_send
+            .send(GameActionRequest {
+                user_id,
+                action,
+                reply,
+            })
+            .map_err(|_| anyhow!("game is not running"))?;
+        Ok(reply_recv)
+    }
+
+    /// all bottom cards, never send this to players, see `visible_bottom`
+    pub fn bottom(&self) -> &Vec<CardCopy> {
+        &self.bottom
+    }
+
+    /// the bottom as `seat` is allowed to see it
+    pub fn visible_bottom(&self, seat: usize) -> Option<&Vec<CardCopy>> {
+        let revealed = matches!(self.phase, GamePhase::Scoring | GamePhase::Finished);
+        let picked_up = matches!(
+            self.phase,
+            GamePhase::ExchangingBottom | GamePhase::CallingPartner | GamePhase::Playing
+        );
+        if revealed || (picked_up && seat == self.declarer) {
+            Some(&self.bottom)
+        } else {
+            None
+        }
+    }
+
+    pub fn record(&self) -> &GameRecord {
+        &self.record
+    }
+
+    pub fn record_event(&mut self, event: GameEvent) {
+        let millis = self
+            .started_at
+            .map_or(0, |started_at| started_at.elapsed().as_millis() as u64);
+        self.record.events.push(RecordedEvent { millis, event });
+    }
+
+    pub fn set_turn_clock(&mut self, turn_clock: TurnClock) {
+        self.turn_clock = Some(turn_clock);
+        self.notify_state_changed();
+    }
+
+    /// whoever the game is waiting for
+    pub fn acting_seat(&self) -> Option<usize> {
+        match self.phase {
+            GamePhase::ExchangingBottom | GamePhase::CallingPartner => Some(self.declarer),
+            GamePhase::Playing => Some(self.cur_turn),
+            _ => None,
+        }
+    }
+
+    pub fn enter_phase(&mut self, phase: GamePhase) {
+        self.phase = phase;
+        // no subscribers is fine
+        let _ = self.phase_change_send.send(phase);
+        self.notify_state_changed();
+    }
+
+    /// deal every card at once
+    pub fn deal(&mut self) {
+        self.start_deal();
+        while self.deal_round() {}
+    }
+
+    /// shuffle and set the bottom aside, hands are then filled by `deal_round`
+    pub fn start_deal(&mut self) {
+        self.started_at = Some(Instant::now());
+        self.record.declarer_fixed = self.declarer_fixed;
+        self.record_event(GameEvent::DealStarted {
+            declarer: self.declarer,
+        });
+        for tool in self.granted_tools(|grant| match grant {
+            ToolGrant::EveryPlayer(tool) => Some(*tool),
+            _ => None,
+        }) {
+            for player in self.players.iter_mut() {
+                player.tools.push(tool);
+            }
+        }
+        let basic_configs = &self.configurable_rules.basic_configs;
+        let player_count = basic_configs.max_player_count as usize;
+        let mut shoe = Shoe::of_configs(basic_configs);
+        shoe.shuffle(&mut StdRng::seed_from_u64(self.seed));
+        self.bottom_size = shoe.bottom_size_of(basic_configs);
+        let deal = shoe.deal(player_count, self.bottom_size);
+        self.undealt = deal.hands;
+        for undealt in self.undealt.iter_mut() {
+            undealt.reverse();
+        }
+        self.bottom = deal.bottom;
+    }
+
+    /// one more card to every player, false once everything is dealt
+    pub fn deal_round(&mut self) -> bool {
+        let mut dealt = false;
+        for (seat, (player, undealt)) in self
+            .players
+            .iter_mut()
+            .zip(self.undealt.iter_mut())
+            .enumerate()
+        {
+            if let Some(card) = undealt.pop() {
+                player.hand.push(card);
+                if let Some(send) = self.dealt_card_send.as_ref() {
+                    // no subscribers is fine
+                    let _ = send.send((seat, card));
+                }
+                dealt = true;
+            }
+        }
+        if !dealt {
+            // closes the streams of dealt cards
+            self.dealt_card_send = None;
+        }
+        dealt
+    }
+
+    /// the hand starts with shopping instead of dealing
+    pub fn open_shop(&mut self, shop: Shop) {
+        self.record.shop = Some(shop.clone());
+        self.shop = Some(shop);
+        self.enter_phase(GamePhase::Shopping);
+    }
+
+    pub fn shop(&self) -> Option<&Shop> {
+        self.shop.as_ref()
+    }
+
+    pub fn close_shop(&mut self) {
+        self.turn_clock = None;
+        self.record_event(GameEvent::ShopClosed);
+        self.enter_phase(GamePhase::Dealing);
+    }
+
+    fn shop_mut(&mut self) -> Result<&mut Shop, Error> {
+        self.shop.as_mut().ok_or(anyhow!("no shop in this hand"))
+    }
+
+    /// bought tools can be used in this hand already, so can the tool of a bought relic
+    pub fn buy(&mut self, seat: usize, index: usize) -> Result<(), Error> {
+        let tool = match self.shop_mut()?.buy(seat, index)? {
+            Goods::Tool(tool) => Some(tool),
+            Goods::Relic(relic) => relic.tool(),
+        };
+        // not recorded as granted, replaying the purchase grants it
+        if let Some(tool) = tool {
+            self.players[seat].tools.push(tool);
+        }
+        Ok(())
+    }
+
+    /// None once dealing is over
+    pub fn subscribe_dealt_cards(&self) -> Option<broadcast::Receiver<(usize, CardCopy)>> {
+        self.dealt_card_send.as_ref().map(|send| send.subscribe())
+    }
+
+    pub fn declare_prime(&mut self, seat: usize, cards: Vec<CardCopy>) -> Result<(), Error> {
+        if !self.players[seat].holds_all(&cards) {
+            return Err(anyhow!("cards not in hand"));
+        }
+        let always_prime_numbers = &self.configurable_rules.play_configs.always_prime_numbers;
+        let declaration = PrimeDeclaration::new(seat, cards, always_prime_numbers)?;
+        if let Some(current) = self.declaration.as_ref() {
+            if !declaration.overrides(current) {
+                return Err(anyhow!("must declare with more copies"));
+            }
+        }
+        self.prime_suit = declaration.prime_suit();
+        if !self.declarer_fixed {
+            self.declarer = seat;
+        }
+        self.declaration = Some(declaration);
+        Ok(())
+    }
+
+    /// once the declaring window is over, the strongest declaration stands
+    pub fn resolve_prime(&mut self) {
+        self.turn_clock = None;
+        if self.declaration.is_none() {
+            self.resolve_prime_by_bottom();
+        }
+        self.record_event(GameEvent::PrimeResolved);
+    }
+
+    /// without any declaration, the first suited bottom card decides the prime suit
+    pub fn resolve_prime_by_bottom(&mut self) {
+        self.prime_suit = self.bottom.iter().find_map(|c| c.card().suit);
+    }
+
+    pub fn pick_up_bottom(&mut self) {
+        let bottom = std::mem::take(&mut self.bottom);
+        self.players[self.declarer].hand.extend(bottom);
+    }
+
+    pub fn bury_bottom(&mut self, seat: usize, cards: Vec<CardCopy>) -> Result<(), Error> {
+        if seat != self.declarer {
+            return Err(anyhow!("only the declarer can bury bottom cards"));
+        }
+        if cards.len() != self.bottom_size {
+            return Err(anyhow!("must bury exactly {} cards", self.bottom_size));
+        }
+        let declarer = &mut self.players[seat];
+        if !declarer.holds_all(&cards) {
+            return Err(anyhow!("cards not in hand"));
+        }
+        declarer.remove_cards(&cards);
+        self.bottom = cards;
+        self.turn_clock = None;
+        self.cur_turn = self.declarer;
+        self.tricks.push(Trick::new(self.declarer));
+        Ok(())
+    }
+
+    pub fn team_formation(&self) -> TeamFormation {
+        self.configurable_rules.play_configs.team_formation
+    }
+
+    /// how many cards the declarer calls, roughly half of the table plays together
+    pub fn called_card_count(&self) -> usize {
+        (self.players.len() / 2).saturating_sub(1)
+    }
+
+    pub fn call_partner(
+        &mut self,
+        seat: usize,
+        called_cards: Vec<CalledCard>,
+    ) -> Result<(), Error> {
+        if seat != self.declarer {
+            return Err(anyhow!("only the declarer can call partners"));
+        }
+        if self.team_formation() != TeamFormation::CalledCard {
+            return Err(anyhow!("partners are not formed by called cards"));
+        }
+        if called_cards.len() != self.called_card_count() {
+            return Err(anyhow!("must call {} cards", self.called_card_count()));
+        }
+        let deck_size = self.configurable_rules.basic_configs.deck_size as u32;
+        for called in called_cards.iter() {
+            if called.intrinsic_id >= DECK_CARD_COUNT || called.nth == 0 || called.nth > deck_size {
+                return Err(anyhow!("invalid called card {:?}", called));
+            }
+        }
+        self.teams.call(called_cards);
+        self.turn_clock = None;
+        Ok(())
+    }
+
+    pub fn power_rule(&self) -> PowerRule {
+        PowerRule::new(
+            self.prime_suit,
+            self.configurable_rules
+                .play_configs
+                .always_prime_numbers
+                .clone(),
+        )
+    }
+
+    pub fn cur_trick(&self) -> Option<&Trick> {
+        self.tricks.last()
+    }
+
+    /// Some if the play was a failed throw
+    pub fn play_cards(
+        &mut self,
+        seat: usize,
+        cards: Vec<CardCopy>,
+    ) -> Result<Option<FailedThrow>, PlayRejection> {
+        if self.phase != GamePhase::Playing {
+            return Err(PlayRejection::NotPlayingPhase);
+        }
+        if seat != self.cur_turn {
+            return Err(PlayRejection::NotYourTurn);
+        }
+        let rule = self.power_rule();
+        let hand = &self.players[seat].hand;
+        let trick = self.tricks.last().ok_or(PlayRejection::NotPlayingPhase)?;
+        let mut cards = cards;
+        let mut failed_throw = None;
+        match trick.lead() {
+            None => {
+                let play_configs = &self.configurable_rules.play_configs;
+                check_lead(hand, &cards, &rule, play_configs.allow_throw)?;
+                if let Some(forced) = self.failed_throw_component(seat, &cards, &rule) {
+                    let failed = FailedThrow {
+                        seat,
+                        attempted: cards,
+                        forced: forced.clone(),
+                    };
+                    self.failed_throws.push(failed.clone());
+                    failed_throw = Some(failed);
+                    cards = forced;
+                }
+            }
+            Some(lead) => check_follow(hand, lead, &cards, &rule)?,
+        }
+        self.players[seat].remove_cards(&cards);
+        self.turn_clock = None;
+        let player_count = self.players.len();
+        self.teams.on_play(seat, &cards, self.declarer);
+        let trick = self.tricks.last_mut().unwrap();
+        trick.plays.push((seat, cards));
+        if trick.plays.len() < player_count {
+            self.cur_turn = self.next_seat(seat);
+            return Ok(failed_throw);
+        }
+        let winner = trick_winner(trick, &rule);
+        trick.winner = Some(winner);
+        trick.points = trick
+            .plays
+            .iter()
+            .map(|(_, c)| points_of(c, &self.configurable_rules.score_configs))
+            .sum();
+        let won: Vec<CardCopy> = trick.plays.iter().flat_map(|(_, c)| c.clone()).collect();
+        self.players[winner].won_cards.extend(won);
+        let points = trick.points;
+        let granted = self.granted_tools(|grant| match grant {
+            ToolGrant::TrickWinner { tool, min_points } if points >= *min_points => Some(*tool),
+            _ => None,
+        });
+        self.players[winner].tools.extend(granted);
+        self.cur_turn = winner;
+        if !self.all_hands_empty() {
+            self.tricks.push(Trick::new(winner));
+        } else if self.team_formation() == TeamFormation::LastTrick {
+            self.teams.on_last_trick(winner, self.declarer);
+        }
+        Ok(failed_throw)
+    }
+
+    /// who plays after `seat` in the current turn order
+    pub fn next_seat(&self, seat: usize) -> usize {
+        let position = self.turn_order.iter().position(|s| *s == seat).unwrap();
+        self.turn_order[(position + 1) % self.turn_order.len()]
+    }
+
+    /// tools handed out by the grants `select` picks, nothing when tools are disabled
+    fn granted_tools(&self, select: impl Fn(&ToolGrant) -> Option<ToolKind>) -> Vec<ToolKind> {
+        let play_configs = &self.configurable_rules.play_configs;
+        if !play_configs.tools_enabled {
+            return vec![];
+        }
+        play_configs.tool_grants.iter().filter_map(select).collect()
+    }
+
+    pub fn grant_tool(&mut self, seat: usize, tool: ToolKind) {
+        self.players[seat].tools.push(tool);
+        self.record_event(GameEvent::ToolGranted { seat, tool });
+    }
+
+    /// a tool can only be used once, by the leader of a trick before leading
+    pub fn use_tool(&mut self, seat: usize, tool_use: ToolUse) -> Result<ToolOutcome, Error> {
+        if !in_tool_window(self, seat) {
+            return Err(anyhow!("tools can only be used before leading a trick"));
+        }
+        let kind = tool_use.kind();
+        let tools = &self.players[seat].tools;
+        let index = tools
+            .iter()
+            .position(|t| *t == kind)
+            .ok_or(anyhow!("no {:?} left", kind))?;
+        let outcome = tool_use.apply(self, seat)?;
+        self.players[seat].tools.remove(index);
+        Ok(outcome)
+    }
+
+    /// a buried bottom card, only shown to `seat` from now on
+    pub fn peek_bottom(&mut self, seat: usize, index: usize) -> Result<CardCopy, Error> {
+        let card = *self
+            .bottom
+            .get(index)
+            .ok_or(anyhow!("no bottom card at {}", index))?;
+        self.players[seat].peeked_bottom.push(card);
+        Ok(card)
+    }
+
+    /// the component a throw is reduced to when any other player could beat part of it
+    fn failed_throw_component(
+        &self,
+        seat: usize,
+        cards: &[CardCopy],
+        rule: &PowerRule,
+    ) -> Option<Vec<CardCopy>> {
+        let throw = Throw::decompose(cards, rule)?;
+        if throw.is_single_combination() {
+            return None;
+        }
+        let other_hands: Vec<Vec<CardCopy>> = self
+            .players
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != seat)
+            .map(|(_, p)| p.hand.clone())
+            .collect();
+        throw
+            .smallest_beaten(&other_hands, rule)
+            .map(|c| c.cards.clone())
+    }
+
+    pub fn all_hands_empty(&self) -> bool {
+        self.players.iter().all(|p| p.hand.is_empty())
+    }
+
+    /// as far as revealed, hidden partners are defenders until they show up
+    pub fn side_of(&self, seat: usize) -> Side {
+        let on_declarer_side = match self.team_formation() {
+            TeamFormation::AlternatingSeats => seat % 2 == self.declarer % 2,
+            TeamFormation::CalledCard | TeamFormation::LastTrick => {
+                seat == self.declarer || self.teams.partners.contains(&seat)
+            }
+        };
+        if on_declarer_side {
+            Side::Declarer
+        } else {
+            Side::Defender
+        }
+    }
+
+    /// apply an action of `seat` and move on to the next phase when it ends the current one,
+    /// every applied action is recorded
+    pub fn apply_action(
+        &mut self,
+        seat: usize,
+        action: GameAction,
+    ) -> Result<ActionOutcome, Error> {
+        let mut outcome = ActionOutcome::Applied;
+        match (self.phase, action.clone()) {
+            (GamePhase::Dealing | GamePhase::DeclaringPrime, GameAction::DeclarePrime(cards)) => {
+                self.declare_prime(seat, cards)?;
+            }
+            (GamePhase::ExchangingBottom, GameAction::BuryBottom(cards)) => {
+                self.bury_bottom(seat, cards)?;
+                if self.team_formation() == TeamFormation::CalledCard {
+                    self.enter_phase(GamePhase::CallingPartner);
+                } else {
+                    self.enter_phase(GamePhase::Playing);
+                }
+            }
+            (GamePhase::CallingPartner, GameAction::CallPartner(called_cards)) => {
+                self.call_partner(seat, called_cards)?;
+                self.enter_phase(GamePhase::Playing);
+            }
+            // rejected by `play_cards` itself outside of playing
+            (_, GameAction::PlayCards(cards)) => {
+                if let Some(failed_throw) = self.play_cards(seat, cards)? {
+                    outcome = ActionOutcome::ThrowFailed(failed_throw);
+                }
+                if self.all_hands_empty() {
+                    self.enter_phase(GamePhase::Scoring);
+                }
+            }
+            (GamePhase::Shopping, GameAction::Buy(index)) => self.buy(seat, index)?,
+            (GamePhase::Shopping, GameAction::Reroll) => self.shop_mut()?.reroll(seat)?,
+            (GamePhase::Shopping, GameAction::LeaveShop) => self.shop_mut()?.leave(seat),
+            (_, GameAction::UseTool(tool_use)) => {
+                outcome = ActionOutcome::ToolUsed(self.use_tool(seat, tool_use)?);
+            }
+            (phase, action) => return Err(anyhow!("cannot {:?} during {:?}", action, phase)),
+        }
+        self.record_event(GameEvent::Acted { seat, action });
+        Ok(outcome)
+    }
+
+    pub fn score(&mut self) {
+        let score_configs = &self.configurable_rules.score_configs;
+        let points_by_seat: Vec<u32> = self
+            .players
+            .iter()
+            .map(|p| points_of(&p.won_cards, score_configs))
+            .collect();
+        let mut defender_points: u32 = points_by_seat
+            .iter()
+            .enumerate()
+            .filter(|(seat, _)| self.side_of(*seat) == Side::Defender)
+            .map(|(_, points)| points)
+            .sum();
+        let mut bottom_points = 0;
+        if let Some(last_winner) = self.tricks.last().and_then(|t| t.winner) {
+            if self.side_of(last_winner) == Side::Defender {
+                bottom_points =
+                    points_of(&self.bottom, score_configs) * score_configs.bottom_multiplier;
+            }
+        }
+        defender_points += bottom_points;
+        // a failed throw gives its penalty to the other side
+        let penalty = self.configurable_rules.play_configs.failed_throw_penalty;
+        let mut penalties_by_seat = vec![0; self.players.len()];
+        for failed in self.failed_throws.iter() {
+            penalties_by_seat[failed.seat] += penalty;
+            match self.side_of(failed.seat) {
+                Side::Declarer => defender_points += penalty,
+                Side::Defender => defender_points = defender_points.saturating_sub(penalty),
+            }
+        }
+        let (winner, level_change) = settle(defender_points, score_configs);
+        self.result = Some(GameResult {
+            points_by_seat,
+            penalties_by_seat,
+            bottom_points,
+            defender_points,
+            winner,
+            level_change,
+        });
+        self.record_event(GameEvent::Scored);
+    }
+}
+
+/// the strongest play shaped like the lead wins, ties go to the earlier play
+fn trick_winner(trick: &Trick, rule: &PowerRule) -> usize {
+    let (leader, lead) = &trick.plays[0];
+    let mut winner = *leader;
+    let mut best = Throw::decompose(lead, rule).expect("lead is always of one prime or sub suit");
+    for (seat, played) in trick.plays.iter().skip(1) {
+        if let Some(throw) = Throw::decompose(played, rule) {
+            if throw.beats(&best) {
+                best = throw;
+                winner = *seat;
+            }
+        }
+    }
+    winner
 }
@@ -0,0 +1,395 @@
+use crate::model::configs::GameConfigurations;
+use crate::model::game::Game;
+use crate::model::game_record::GameRecord;
+use crate::model::run::{RunState, RunSummary};
+use crate::model::user::User;
+use crate::rules::scoring::Side;
+use anyhow::{anyhow, Error};
+use parking_lot::RwLock;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::watch;
+
+#[tokio::test]
+async fn test_match_ends() {
+    use crate::engine::{GameEngine, GameEngineTestImpl};
+    use crate::model::game::test_users;
+    async fn play(configs: GameConfigurations) -> Match {
+        let mut game_match = Match::new(test_users(4), configs, Some(42));
+        while !game_match.is_finished() {
+            let game = game_match.next_game();
+            GameEngineTestImpl::default().run_game(game.clone()).await;
+            game_match.record(&game.read());
+        }
+        game_match
+    }
+    let mut configs = GameConfigurations::default();
+    configs.basic_configs.max_player_count = 4;
+    configs.match_configs.max_hands = Some(3);
+    let game_match = play(configs.clone()).await;
+    let summary = game_match.summary();
+    assert!(summary.finished);
+    // nobody reaches the target level, the hands run out
+    assert_eq!(summary.hands.len(), 3);
+    assert_eq!(game_match.records.len(), 3);
+    assert!(summary.levels.iter().all(|level| *level < 6));
+    let total: u32 = summary.hands.iter().map(|h| h.level_change * 2).sum();
+    assert_eq!(summary.levels.iter().sum::<u32>(), total);
+    configs.match_configs.target_level = 2;
+    configs.match_configs.max_hands = None;
+    let summary = play(configs).await.summary();
+    // the target level ends the match before any limit of hands
+    assert!(summary.finished);
+    assert_eq!(summary.hands.len(), 2);
+    assert!(summary.levels.iter().any(|level| *level >= 2));
+}
+
+#[test]
+fn test_take_over_seat() {
+    use crate::model::game::test_users;
+    let mut configs = GameConfigurations::default();
+    configs.basic_configs.max_player_count = 4;
+    let users = test_users(4);
+    let mut game_match = Match::new(users.clone(), configs, Some(42));
+    game_match.levels[2] = 3;
+    let newcomer = Arc::new(RwLock::new(User {
+        id: 100,
+        ..Default::default()
+    }));
+    let all_seated: Vec<u32> = users.iter().map(|u| u.read().id).collect();
+    assert!(game_match
+        .take_over_seat(newcomer.clone(), &all_seated)
+        .is_err());
+    // the user of seat 2 left
+    let seated: Vec<u32> = all_seated
+        .iter()
+        .cloned()
+        .filter(|id| *id != all_seated[2])
+        .collect();
+    assert_eq!(game_match.take_over_seat(newcomer, &seated).unwrap(), 2);
+    let game = game_match.next_game();
+    assert_eq!(game.read().seat_of(100), Some(2));
+    assert_eq!(game_match.levels[2], 3);
+}
+
+#[tokio::test]
+async fn test_declarer_rotation() {
+    use crate::engine::{GameEngine, GameEngineTestImpl};
+    use crate::model::game::{test_users, GamePhase};
+    use crate::model::game_view::LegalMoves;
+    let mut configs = GameConfigurations::default();
+    configs.basic_configs.max_player_count = 4;
+    let mut game_match = Match::new(test_users(4), configs, Some(42));
+    let game = game_match.next_game();
+    GameEngineTestImpl::default().run_game(game.clone()).await;
+    game_match.record(&game.read());
+    let first = &game_match.hands[0];
+    let expected = match first.winner {
+        Side::Declarer => first.declarer,
+        Side::Defender => (first.declarer + 1) % 4,
+    };
+    let game = game_match.next_game();
+    let mut game = game.write();
+    assert_eq!(game.declarer, expected);
+    game.deal();
+    game.enter_phase(GamePhase::DeclaringPrime);
+    // whoever else declares only decides the prime suit
+    let (seat, cards) = (0..4)
+        .filter(|seat| *seat != expected)
+        .find_map(|seat| match game.legal_moves(seat) {
+            LegalMoves::DeclarePrime { candidates } => Some((seat, candidates[0].clone())),
+            _ => None,
+        })
+        .unwrap();
+    game.declare_prime(seat, cards).unwrap();
+    assert!(game.prime_suit.is_some());
+    assert_eq!(game.declarer, expected);
+}
+
+#[tokio::test]
+async fn test_run_ends_on_defeat() {
+    use crate::engine::{GameEngine, GameEngineTestImpl};
+    use crate::model::configs::RunConfigurations;
+    use crate::model::game::test_users;
+    let mut configs = GameConfigurations::default();
+    configs.basic_configs.max_player_count = 4;
+    configs.match_configs.max_hands = None;
+    configs.match_configs.run = Some(RunConfigurations {
+        lives: 2,
+        ..Default::default()
+    });
+    let users = test_users(4);
+    let mut game_match = Match::new(users.clone(), configs, Some(42));
+    while !game_match.is_finished() {
+        let game = game_match.next_game();
+        GameEngineTestImpl::default().run_game(game.clone()).await;
+        game_match.record(&game.read());
+        if !game_match.is_finished() {
+            assert_eq!(game_match.run.as_ref().unwrap().offers[0].len(), 3);
+            game_match.choose_relic(0, 1).unwrap();
+            assert!(game_match.choose_relic(0, 0).is_err());
+            game_match.run.as_mut().unwrap().choose_for_the_rest();
+        }
+    }
+    let run = game_match.summary().run.unwrap();
+    assert!(!run.defeated.is_empty());
+    assert_eq!(run.relics[0].len(), game_match.hands.len() - 1);
+    for (user, score) in users.iter().zip(run.scores.iter()) {
+        assert_eq!(user.read().best_run_score, *score);
+    }
+    // every hand but the first opens with a shop, and replays with it
+    let last = game_match.records.last().unwrap();
+    assert_eq!(last.shop.is_some(), game_match.hands.len() > 1);
+    let replayed = crate::engine::replay_game(last).unwrap();
+    assert_eq!(replayed.phase, crate::model::game::GamePhase::Finished);
+}
+
+#[tokio::test]
+async fn test_relic_bought_right_away() {
+    use crate::engine::{GameEngine, GameEngineTestImpl};
+    use crate::model::configs::RunConfigurations;
+    use crate::model::game::{test_users, GameAction};
+    use crate::model::run::Relic;
+    use crate::model::shop::Goods;
+    let mut configs = GameConfigurations::default();
+    configs.basic_configs.max_player_count = 4;
+    configs.play_configs.tools_enabled = true;
+    configs.match_configs.run = Some(RunConfigurations::default());
+    let mut game_match = Match::new(test_users(4), configs, Some(42));
+    let game = game_match.next_game();
+    GameEngineTestImpl::default().run_game(game.clone()).await;
+    game_match.record(&game.read());
+    game_match.run.as_mut().unwrap().coins[0] = 100;
+    let game = game_match.next_game();
+    let mut game = game.write();
+    let (index, relic) = loop {
+        let toolbox = game.shop().unwrap().items[0]
+            .iter()
+            .enumerate()
+            .find_map(|(i, item)| match item.goods {
+                Goods::Relic(relic @ Relic::Toolbox(_)) => Some((i, relic)),
+                _ => None,
+            });
+        match toolbox {
+            Some(found) => break found,
+            None => {
+                game.apply_action(0, GameAction::Reroll).unwrap();
+            }
+        }
+    };
+    game.apply_action(0, GameAction::Buy(index)).unwrap();
+    game_match.sync_shop(&game);
+    game_match.sync_shop(&game);
+    let run = game_match.run.as_ref().unwrap();
+    assert_eq!(run.relics[0].iter().filter(|r| **r == relic).count(), 1);
+    assert_eq!(run.coins[0], game.shop().unwrap().coins[0]);
+    assert!(game.players()[0].tools.contains(&relic.tool().unwrap()));
+}
+
+/// a sitting of several hands, levels are kept by seat since sides change from hand to hand
+pub struct Match {
+    users: Vec<Arc<RwLock<User>>>,
+    configs: GameConfigurations,
+    /// hands are dealt from this seed and their index, None to deal randomly
+    seed: Option<u64>,
+    pub levels: Vec<u32>,
+    pub hands: Vec<HandSummary>,
+    /// every hand played, abandoned ones included
+    pub records: Vec<GameRecord>,
+    /// declarer of the next hand, only the first hand goes to whoever declares the prime suit
+    pub next_declarer: usize,
+    /// only in run mode
+    pub run: Option<RunState>,
+    /// counts relics chosen, so the room can wait for everyone to choose
+    relic_choice_send: watch::Sender<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HandSummary {
+    pub declarer: usize,
+    pub winner: Side,
+    /// seats on the winning side
+    pub winners: Vec<usize>,
+    pub defender_points: u32,
+    pub level_change: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MatchSummary {
+    /// by seat
+    pub levels: Vec<u32>,
+    pub hands: Vec<HandSummary>,
+    pub finished: bool,
+    /// seats with the highest level
+    pub leaders: Vec<usize>,
+    pub run: Option<RunSummary>,
+}
+
+impl Match {
+    pub fn new(
+        users: Vec<Arc<RwLock<User>>>,
+        configs: GameConfigurations,
+        seed: Option<u64>,
+    ) -> Self {
+        let levels = vec![0; users.len()];
+        let run = configs.match_configs.run.clone().map(|run_configs| {
+            let rng = match seed {
+                None => StdRng::from_entropy(),
+                Some(seed) => StdRng::seed_from_u64(seed),
+            };
+            let tools_enabled = configs.play_configs.tools_enabled;
+            RunState::new(run_configs, tools_enabled, rng, users.len())
+        });
+        Self {
+            users,
+            configs,
+            seed,
+            levels,
+            hands: vec![],
+            records: vec![],
+            next_declarer: 0,
+            run,
+            relic_choice_send: watch::channel(0).0,
+        }
+    }
+
+    pub fn configs(&self) -> &GameConfigurations {
+        &self.configs
+    }
+
+    pub fn is_finished(&self) -> bool {
+        let match_configs = &self.configs.match_configs;
+        let reached_target = self
+            .levels
+            .iter()
+            .any(|level| *level >= match_configs.target_level);
+        let out_of_hands = match_configs
+            .max_hands
+            .is_some_and(|max_hands| self.hands.len() >= max_hands as usize);
+        match self.run.as_ref() {
+            None => reached_target || out_of_hands,
+            Some(run) => run.is_over() || out_of_hands,
+        }
+    }
+
+    /// a seated user picks one of the relics offered before the next hand
+    pub fn choose_relic(&mut self, user_id: u32, index: usize) -> Result<(), Error> {
+        let seat = self
+            .users
+            .iter()
+            .position(|u| u.read().id == user_id)
+            .ok_or(anyhow!("user is not playing"))?;
+        let run = self.run.as_mut().ok_or(anyhow!("not a run"))?;
+        run.choose(seat, index)?;
+        self.relic_choice_send.send_modify(|count| *count += 1);
+        Ok(())
+    }
+
+    pub fn subscribe_relic_choices(&self) -> watch::Receiver<usize> {
+        self.relic_choice_send.subscribe()
+    }
+
+    /// a new user takes over the first seat whose user is no longer `seated`, levels stay by seat
+    pub fn take_over_seat(
+        &mut self,
+        user: Arc<RwLock<User>>,
+        seated: &[u32],
+    ) -> Result<usize, Error> {
+        let seat = self
+            .users
+            .iter()
+            .position(|u| !seated.contains(&u.read().id))
+            .ok_or(anyhow!("no seat was left"))?;
+        self.users[seat] = user;
+        Ok(seat)
+    }
+
+    pub fn next_game(&self) -> Arc<RwLock<Game>> {
+        let users = self.users.clone();
+        let configs = self.configs.clone();
+        let mut game = match self.seed {
+            None => Game::new(users, configs),
+            Some(seed) => {
+                Game::with_seed(users, configs, seed.wrapping_add(self.hands.len() as u64))
+            }
+        };
+        game.declarer = self.next_declarer;
+        game.declarer_fixed = !self.hands.is_empty();
+        if let Some(run) = self.run.as_ref() {
+            run.on_hand_start(&mut game);
+            if !self.hands.is_empty() {
+                run.open_shop(&mut game);
+            }
+        }
+        Arc::new(RwLock::new(game))
+    }
+
+    /// relics bought in the shop of the hand count right away, called after every shop action
+    pub fn sync_shop(&mut self, game: &Game) {
+        if let (Some(run), Some(shop)) = (self.run.as_mut(), game.shop()) {
+            run.sync_shop(shop);
+        }
+    }
+
+    /// winners go up by the level change, the declarer keeps the bottom only if their side won
+    pub fn record(&mut self, game: &Game) {
+        self.records.push(game.record().clone());
+        self.sync_shop(game);
+        let Some(result) = game.result.as_ref() else {
+            return;
+        };
+        let winners: Vec<usize> = (0..self.levels.len())
+            .filter(|seat| game.side_of(*seat) == result.winner)
+            .collect();
+        for seat in winners.iter() {
+            self.levels[*seat] += result.level_change;
+        }
+        self.next_declarer = match result.winner {
+            Side::Declarer => game.declarer,
+            Side::Defender => (game.declarer + 1) % self.levels.len(),
+        };
+        self.hands.push(HandSummary {
+            declarer: game.declarer,
+            winner: result.winner,
+            winners,
+            defender_points: result.defender_points,
+            level_change: result.level_change,
+        });
+        if let Some(run) = self.run.as_mut() {
+            let won_by_seat: Vec<bool> = (0..self.levels.len())
+                .map(|seat| game.side_of(seat) == result.winner)
+                .collect();
+            run.record_hand(&won_by_seat, &result.points_by_seat);
+        }
+        if self.is_finished() {
+            self.record_best_run_scores();
+        } else if let Some(run) = self.run.as_mut() {
+            run.make_offers();
+        }
+    }
+
+    fn record_best_run_scores(&self) {
+        let Some(run) = self.run.as_ref() else {
+            return;
+        };
+        for (user, score) in self.users.iter().zip(run.scores.iter()) {
+            let mut user = user.write();
+            user.best_run_score = user.best_run_score.max(*score);
+        }
+    }
+
+    pub fn summary(&self) -> MatchSummary {
+        let top = self.levels.iter().max().copied().unwrap_or(0);
+        MatchSummary {
+            levels: self.levels.clone(),
+            hands: self.hands.clone(),
+            finished: self.is_finished(),
+            leaders: (0..self.levels.len())
+                .filter(|seat| self.levels[*seat] == top)
+                .collect(),
+            run: self.run.as_ref().map(|run| run.summary()),
+        }
+    }
+}
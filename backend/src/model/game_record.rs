@@ -0,0 +1,73 @@
+use crate::model::configs::GameConfigurations;
+use crate::model::game::GameAction;
+use crate::model::shop::Shop;
+use crate::model::tool::ToolKind;
+use serde::{Deserialize, Serialize};
+
+/// everything that happened in a hand, in order, enough to play it again exactly
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GameRecord {
+    pub seed: u64,
+    /// configurations as they were when the hand was created
+    pub configs: GameConfigurations,
+    /// by seat
+    pub user_ids: Vec<u32>,
+    /// see `Game::declarer_fixed`
+    #[serde(default)]
+    pub declarer_fixed: bool,
+    /// the shop as it opened before the hand
+    pub shop: Option<Shop>,
+    pub events: Vec<RecordedEvent>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RecordedEvent {
+    /// since dealing started
+    pub millis: u64,
+    pub event: GameEvent,
+}
+
+/// every change of a hand, actions of players are only recorded once applied
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum GameEvent {
+    /// cards are shuffled by the seed, `declarer` is who owns the bottom unless someone declares
+    DealStarted {
+        declarer: usize,
+    },
+    Acted {
+        seat: usize,
+        action: GameAction,
+    },
+    /// the declaring window is over, the declarer picks up the bottom
+    PrimeResolved,
+    /// the player ran out of time, the next action of the seat was made for them
+    TimedOut {
+        seat: usize,
+    },
+    /// not even an action searched for the seat was accepted, the hand is abandoned
+    AutoPlayRejected {
+        seat: usize,
+        reason: String,
+    },
+    /// everyone left the shop or its time is up, dealing starts
+    ShopClosed,
+    Scored,
+    /// a tool given outside of the configured grants, by a relic of a run
+    ToolGranted {
+        seat: usize,
+        tool: ToolKind,
+    },
+}
+
+impl GameRecord {
+    pub fn new(seed: u64, configs: GameConfigurations, user_ids: Vec<u32>) -> Self {
+        Self {
+            seed,
+            configs,
+            user_ids,
+            declarer_fixed: false,
+            shop: None,
+            events: vec![],
+        }
+    }
+}
@@ -0,0 +1,242 @@
+use crate::model::game::{FailedThrow, Game, GamePhase, GameResult, Trick, TurnClock};
+use crate::model::poker::{CardCopy, Suit};
+use crate::model::tool::ToolKind;
+use crate::rules::declare::PrimeDeclaration;
+use crate::rules::follow::legal_follows;
+use crate::rules::group_identical;
+use crate::rules::scoring::points_of;
+use crate::rules::team::CalledCard;
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+
+#[test]
+fn test_view_hides_other_hands() {
+    use crate::model::configs::GameConfigurations;
+    use crate::model::game::test_users;
+    let mut game = Game::with_seed(test_users(6), GameConfigurations::default(), 42);
+    game.deal();
+    game.enter_phase(GamePhase::DeclaringPrime);
+    let view = game.view_of(1);
+    assert_eq!(view.hand, game.players()[1].hand);
+    assert!(view.players.iter().all(|p| p.card_count == 35));
+    assert!(view.bottom.is_none());
+    game.resolve_prime();
+    game.pick_up_bottom();
+    game.enter_phase(GamePhase::ExchangingBottom);
+    assert!(game.view_of(game.declarer).bottom.is_some());
+    assert!(matches!(
+        game.view_of(game.declarer).legal_moves,
+        LegalMoves::BuryBottom { count: 6 }
+    ));
+    assert!(game.view_of(1).bottom.is_none());
+    assert!(matches!(game.view_of(1).legal_moves, LegalMoves::Wait));
+    let public = game.public_view();
+    assert!(public.seat.is_none() && public.hand.is_empty() && public.bottom.is_none());
+}
+
+/// everything one player is allowed to know about the game
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GameView {
+    /// None for spectators, who only see public information
+    pub seat: Option<usize>,
+    pub phase: GamePhase,
+    pub prime_suit: Option<Suit>,
+    pub declaration: Option<PrimeDeclaration>,
+    pub declarer: usize,
+    /// by seat
+    pub players: Vec<PlayerInGameInfo>,
+    pub hand: Vec<CardCopy>,
+    /// only seen by the declarer holding it, and by everybody once the hand is scored
+    pub bottom: Option<Vec<CardCopy>>,
+    pub bottom_size: usize,
+    pub tricks: Vec<Trick>,
+    pub cur_turn: usize,
+    /// seats in the order they play
+    pub turn_order: Vec<usize>,
+    pub failed_throws: Vec<FailedThrow>,
+    pub called_cards: Vec<CalledCard>,
+    /// revealed partners of the declarer
+    pub partners: Vec<usize>,
+    pub result: Option<GameResult>,
+    pub legal_moves: LegalMoves,
+    /// tools of the player, usable only before leading a trick
+    pub tools: Vec<ToolKind>,
+    pub peeked_bottom: Vec<CardCopy>,
+    pub countdown: Option<Countdown>,
+}
+
+/// time left for the player the game is waiting for, counted down by the client
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Countdown {
+    /// None when anybody can act
+    pub seat: Option<usize>,
+    pub remaining_millis: u64,
+    /// reserve left, only used up once `remaining_millis` reaches 0
+    pub reserve_millis: u64,
+    /// waiting for a disconnected player, the countdown does not run
+    pub paused: bool,
+}
+
+impl From<&TurnClock> for Countdown {
+    fn from(value: &TurnClock) -> Self {
+        let now = Instant::now();
+        let overtime = now.saturating_duration_since(value.deadline);
+        Self {
+            seat: value.seat,
+            remaining_millis: value.deadline.saturating_duration_since(now).as_millis() as u64,
+            reserve_millis: value.reserve.saturating_sub(overtime).as_millis() as u64,
+            paused: value.paused,
+        }
+    }
+}
+
+/// what everybody can see of a player
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PlayerInGameInfo {
+    pub user_id: u32,
+    pub nick_name: String,
+    pub card_count: usize,
+    /// points of the tricks won so far
+    pub points: u32,
+    pub online: bool,
+    pub tool_count: usize,
+}
+
+/// what a player can do right now
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum LegalMoves {
+    /// nothing to do but wait for others
+    Wait,
+    /// each candidate is strong enough to override the current declaration
+    DeclarePrime {
+        candidates: Vec<Vec<CardCopy>>,
+    },
+    BuryBottom {
+        count: usize,
+    },
+    CallPartner {
+        count: usize,
+    },
+    /// any cards of the same prime or sub suit, a single combination when throws are not allowed
+    Lead {
+        allow_throw: bool,
+    },
+    /// exactly `count` cards out of `playable`,
+    /// including all of `required` and at least `pairs_required` pairs of the led prime or sub suit
+    Follow {
+        count: usize,
+        playable: Vec<CardCopy>,
+        required: Vec<CardCopy>,
+        pairs_required: usize,
+    },
+}
+
+impl Game {
+    pub fn view_of(&self, seat: usize) -> GameView {
+        self.view(Some(seat))
+    }
+
+    /// what spectators see, no hand and the bottom only once the hand is scored
+    pub fn public_view(&self) -> GameView {
+        self.view(None)
+    }
+
+    fn view(&self, seat: Option<usize>) -> GameView {
+        let score_configs = &self.configurable_rules().score_configs;
+        GameView {
+            seat,
+            phase: self.phase,
+            prime_suit: self.prime_suit,
+            declaration: self.declaration.clone(),
+            declarer: self.declarer,
+            players: self
+                .players()
+                .iter()
+                .map(|p| {
+                    let user = p.user.read();
+                    PlayerInGameInfo {
+                        user_id: user.id,
+                        nick_name: user.nick_name.clone(),
+                        card_count: p.hand.len(),
+                        points: points_of(&p.won_cards, score_configs),
+                        online: user.is_online(),
+                        tool_count: p.tools.len(),
+                    }
+                })
+                .collect(),
+            hand: seat.map_or(vec![], |seat| self.players()[seat].hand.clone()),
+            bottom: match seat {
+                Some(seat) => self.visible_bottom(seat).cloned(),
+                None if matches!(self.phase, GamePhase::Scoring | GamePhase::Finished) => {
+                    Some(self.bottom().clone())
+                }
+                None => None,
+            },
+            bottom_size: self.bottom_size,
+            tricks: self.tricks.clone(),
+            cur_turn: self.cur_turn,
+            turn_order: self.turn_order.clone(),
+            failed_throws: self.failed_throws.clone(),
+            called_cards: self.teams.called_cards.clone(),
+            partners: self.teams.partners.clone(),
+            result: self.result.clone(),
+            legal_moves: seat.map_or(LegalMoves::Wait, |seat| self.legal_moves(seat)),
+            tools: seat.map_or(vec![], |seat| self.players()[seat].tools.clone()),
+            peeked_bottom: seat.map_or(vec![], |seat| self.players()[seat].peeked_bottom.clone()),
+            countdown: self.turn_clock.as_ref().map(Countdown::from),
+        }
+    }
+
+    pub fn legal_moves(&self, seat: usize) -> LegalMoves {
+        match self.phase {
+            GamePhase::Dealing | GamePhase::DeclaringPrime => {
+                let candidates = self.declaration_candidates(seat);
+                if candidates.is_empty() {
+                    LegalMoves::Wait
+                } else {
+                    LegalMoves::DeclarePrime { candidates }
+                }
+            }
+            GamePhase::ExchangingBottom if seat == self.declarer => LegalMoves::BuryBottom {
+                count: self.bottom_size,
+            },
+            GamePhase::CallingPartner if seat == self.declarer => LegalMoves::CallPartner {
+                count: self.called_card_count(),
+            },
+            GamePhase::Playing if seat == self.cur_turn => {
+                let lead = self.cur_trick().and_then(|t| t.lead());
+                match lead {
+                    None => LegalMoves::Lead {
+                        allow_throw: self.configurable_rules().play_configs.allow_throw,
+                    },
+                    Some(lead) => {
+                        let rule = self.power_rule();
+                        let legal = legal_follows(&self.players()[seat].hand, lead, &rule);
+                        LegalMoves::Follow {
+                            count: legal.count,
+                            playable: legal.playable,
+                            required: legal.required,
+                            pairs_required: legal.pairs_required,
+                        }
+                    }
+                }
+            }
+            _ => LegalMoves::Wait,
+        }
+    }
+
+    /// all copies of each qualifying card in hand, as long as they override the current declaration
+    fn declaration_candidates(&self, seat: usize) -> Vec<Vec<CardCopy>> {
+        let always_prime_numbers = &self.configurable_rules().play_configs.always_prime_numbers;
+        group_identical(&self.players()[seat].hand)
+            .into_iter()
+            .filter_map(|group| PrimeDeclaration::new(seat, group, always_prime_numbers).ok())
+            .filter(|d| {
+                self.declaration
+                    .as_ref()
+                    .is_none_or(|current| d.overrides(current))
+            })
+            .map(|d| d.cards)
+            .collect()
+    }
+}
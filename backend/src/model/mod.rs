@@ -1,7 +1,14 @@
 pub mod baodatui_poker;
 pub mod configs;
 pub mod game;
+pub mod game_match;
+pub mod game_record;
+pub mod game_view;
 pub mod poker;
+pub mod preset;
 pub mod room;
-mod tool;
+pub mod run;
+pub mod shoe;
+pub mod shop;
+pub mod tool;
 pub mod user;
@@ -1,6 +1,10 @@
 use enum_iterator::{all, Sequence};
+use serde::{Deserialize, Serialize};
 use std::sync::OnceLock;
 
+/// cards in a single deck, jokers included
+pub const DECK_CARD_COUNT: u32 = 54;
+
 static CARDS: OnceLock<Cards> = OnceLock::new();
 
 pub fn cards() -> &'static Cards {
@@ -19,7 +23,7 @@ pub enum Color {
     BLACK,
 }
 
-#[derive(Debug, Sequence, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Sequence, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Suit {
     DIAMONDS,
     CLUBS,
@@ -65,7 +69,40 @@ impl Default for Cards {
 
 impl Cards {
     pub fn by_id(&self, id: u32) -> &Card {
-        &self.cards[(id as usize) % 54]
+        &self.cards[(id % DECK_CARD_COUNT) as usize]
+    }
+}
+
+/// one physical card of a multi-deck shoe, two copies of the same card never share an id
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct CardCopy {
+    /// `deck_index * 54 + intrinsic_id`
+    pub id: u32,
+}
+
+impl CardCopy {
+    pub fn new(deck_index: u32, intrinsic_id: u32) -> Self {
+        Self {
+            id: deck_index * DECK_CARD_COUNT + intrinsic_id,
+        }
+    }
+
+    /// a suited card, `number` is 1 for aces up to 13 for kings
+    pub fn of(deck_index: u32, suit: Suit, number: u32) -> Self {
+        Self::new(deck_index, suit as u32 * 13 + number - 1)
+    }
+
+    pub fn deck_index(&self) -> u32 {
+        self.id / DECK_CARD_COUNT
+    }
+
+    pub fn intrinsic_id(&self) -> u32 {
+        self.id % DECK_CARD_COUNT
+    }
+
+    pub fn card(&self) -> &'static Card {
+        cards().by_id(self.id)
     }
 }
 
@@ -116,57 +153,4 @@ impl Card {
     pub fn is_joker(&self) -> bool {
         self.intrinsic_id == 52 || self.intrinsic_id == 53
     }
-
-    // pub fn is_prime(&self, prime_suit: Option<Suit>) -> bool {
-    //     if self.is_flex_prime() {
-    //         return true;
-    //     }
-    //     return match prime_suit {
-    //         Some(p) => p == self.suit.unwrap(),
-    //         None => false,
-    //     };
-    // }
-
-    // pub fn is_prime_suit(&self, prime_suit: Option<Suit>) -> bool {
-    //     self.suit == prime_suit
-    // }
-    //
-    // pub fn is_flex_prime(&self) -> bool {
-    //     if self.is_joker() {
-    //         return true;
-    //     }
-    //     let num = self.numeric_card_num.unwrap();
-    //     num == 2 || num == 3 || num == 5
-    // }
-
-    // pub fn is_prime_five(&self, prime_suit: Option<Suit>) -> bool {
-    //     if self.is_joker() {
-    //         return false;
-    //     }
-    //     if self.numeric_card_num.unwrap() != 5 {
-    //         return false;
-    //     }
-    //     return match prime_suit {
-    //         Some(s) => s == self.suit.unwrap(),
-    //         None => false,
-    //     };
-    // }
-    //
-    // pub fn real_power(&self, prime_suit: Option<Suit>) -> i32 {
-    //     if self.is_prime_five(prime_suit) {
-    //         return 10000000;
-    //     }
-    //     let mut pow = self.raw_power;
-    //     if self.is_prime(prime_suit) {
-    //         if self.is_flex_prime() {
-    //             pow *= 1000;
-    //             if self.is_prime_suit(prime_suit) {
-    //                 pow += 1;
-    //             }
-    //         } else {
-    //             pow *= 100;
-    //         }
-    //     }
-    //     pow
-    // }
 }
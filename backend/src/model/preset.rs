@@ -0,0 +1,189 @@
+use crate::model::configs::{GameConfigurations, RunConfigurations};
+use crate::model::tool::{ToolGrant, ToolKind};
+use crate::model::user::User;
+use anyhow::{anyhow, Error};
+use serde::{Deserialize, Serialize};
+
+#[test]
+fn test_built_in_presets() {
+    for preset in built_in_presets() {
+        assert!(preset.built_in);
+        preset.configs.validate().unwrap();
+    }
+    assert!(built_in_preset(QUICK_PRESET).is_some());
+    assert!(built_in_preset("unknown").is_none());
+}
+
+#[test]
+fn test_user_presets() {
+    let mut user = User::default();
+    let mut configs = GameConfigurations::default();
+    configs.basic_configs.max_player_count = 4;
+    assert!(user.save_preset(CLASSIC_PRESET, configs.clone()).is_err());
+    assert!(user.save_preset("", configs.clone()).is_err());
+    user.save_preset("four", configs.clone()).unwrap();
+    configs.basic_configs.max_player_count = 8;
+    user.save_preset("four", configs).unwrap();
+    assert_eq!(user.saved_presets.len(), 1);
+    assert_eq!(user.presets().len(), built_in_presets().len() + 1);
+    user.apply_preset("four").unwrap();
+    assert_eq!(
+        user.preferred_game_config
+            .as_ref()
+            .unwrap()
+            .basic_configs
+            .max_player_count,
+        8
+    );
+    assert!(user.delete_preset(CLASSIC_PRESET).is_err());
+    user.delete_preset("four").unwrap();
+    assert!(user.delete_preset("four").is_err());
+}
+
+pub const CLASSIC_PRESET: &str = "classic";
+pub const QUICK_PRESET: &str = "quick";
+pub const BEGINNER_PRESET: &str = "beginner";
+pub const CHAOS_WITH_ITEMS_PRESET: &str = "chaos-with-items";
+
+const MAX_PRESET_NAME_LEN: usize = 20;
+
+/// named game configurations, users can save their own next to the built-in ones
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ConfigPreset {
+    pub name: String,
+    pub configs: GameConfigurations,
+    /// built-in presets cannot be overwritten or deleted
+    #[serde(default)]
+    pub built_in: bool,
+}
+
+impl ConfigPreset {
+    fn built_in(name: &str, configs: GameConfigurations) -> Self {
+        Self {
+            name: name.to_string(),
+            configs,
+            built_in: true,
+        }
+    }
+}
+
+pub fn built_in_presets() -> Vec<ConfigPreset> {
+    vec![
+        ConfigPreset::built_in(CLASSIC_PRESET, GameConfigurations::default()),
+        ConfigPreset::built_in(QUICK_PRESET, quick()),
+        ConfigPreset::built_in(BEGINNER_PRESET, beginner()),
+        ConfigPreset::built_in(CHAOS_WITH_ITEMS_PRESET, chaos_with_items()),
+    ]
+}
+
+pub fn built_in_preset(name: &str) -> Option<ConfigPreset> {
+    built_in_presets().into_iter().find(|p| p.name == name)
+}
+
+/// fewer hands and shorter turns
+fn quick() -> GameConfigurations {
+    let mut configs = GameConfigurations::default();
+    let time = &mut configs.time_configs;
+    time.deal_interval_millis = 100;
+    time.declare_prime_secs = 8;
+    time.bury_bottom_secs = 30;
+    time.call_partner_secs = 15;
+    time.play_turn_secs = 10;
+    time.reserve_secs = Some(30);
+    configs.match_configs.target_level = 3;
+    configs.match_configs.max_hands = Some(4);
+    configs
+}
+
+/// a small table with 2 decks, no throws and plenty of time
+fn beginner() -> GameConfigurations {
+    let mut configs = GameConfigurations::default();
+    configs.basic_configs.max_player_count = 4;
+    configs.basic_configs.deck_size = 2;
+    configs.play_configs.allow_throw = false;
+    configs.play_configs.failed_throw_penalty = 0;
+    let time = &mut configs.time_configs;
+    time.declare_prime_secs = 30;
+    time.bury_bottom_secs = 120;
+    time.call_partner_secs = 60;
+    time.play_turn_secs = 40;
+    time.reserve_secs = Some(120);
+    time.pause_on_disconnect = true;
+    configs.score_configs.defender_win_threshold = 80;
+    configs.score_configs.level_step = 20;
+    configs
+}
+
+/// a run with tools, relics and the shop
+fn chaos_with_items() -> GameConfigurations {
+    let mut configs = GameConfigurations::default();
+    configs.play_configs.tools_enabled = true;
+    configs
+        .play_configs
+        .tool_grants
+        .push(ToolGrant::TrickWinner {
+            tool: ToolKind::ExchangeLocation,
+            min_points: 20,
+        });
+    configs.match_configs.run = Some(RunConfigurations::default());
+    configs
+}
+
+impl User {
+    /// built-in presets first, then the ones saved by the user
+    pub fn presets(&self) -> Vec<ConfigPreset> {
+        let mut presets = built_in_presets();
+        presets.extend(self.saved_presets.iter().cloned());
+        presets
+    }
+
+    pub fn find_preset(&self, name: &str) -> Option<ConfigPreset> {
+        self.presets().into_iter().find(|p| p.name == name)
+    }
+
+    /// a saved preset with the same name is replaced
+    pub fn save_preset(&mut self, name: &str, configs: GameConfigurations) -> Result<(), Error> {
+        if name.is_empty() || name.chars().count() > MAX_PRESET_NAME_LEN {
+            return Err(anyhow!(
+                "preset name must have 1 to {} characters",
+                MAX_PRESET_NAME_LEN
+            ));
+        }
+        if built_in_preset(name).is_some() {
+            return Err(anyhow!("cannot overwrite built-in preset {}", name));
+        }
+        configs.validate()?;
+        let preset = ConfigPreset {
+            name: name.to_string(),
+            configs,
+            built_in: false,
+        };
+        match self.saved_presets.iter_mut().find(|p| p.name == name) {
+            Some(saved) => *saved = preset,
+            None => self.saved_presets.push(preset),
+        }
+        Ok(())
+    }
+
+    pub fn delete_preset(&mut self, name: &str) -> Result<(), Error> {
+        if built_in_preset(name).is_some() {
+            return Err(anyhow!("cannot delete built-in preset {}", name));
+        }
+        let index = self
+            .saved_presets
+            .iter()
+            .position(|p| p.name == name)
+            .ok_or(anyhow!("preset not found {}", name))?;
+        self.saved_presets.remove(index);
+        Ok(())
+    }
+
+    /// the preset becomes preferred, rooms created by the user start with it
+    pub fn apply_preset(&mut self, name: &str) -> Result<GameConfigurations, Error> {
+        let preset = self
+            .find_preset(name)
+            .ok_or(anyhow!("preset not found {}", name))?;
+        self.preferred_game_config = Some(preset.configs.clone());
+        Ok(preset.configs)
+    }
+}
@@ -1,5 +1,6 @@
 use crate::model::configs::GameConfigurations;
 use crate::model::game::Game;
+use crate::model::game_match::{Match, MatchSummary};
 use crate::model::user::User;
 use crate::utils::WatcherWrapper;
 use baodatui_macro::ID;
@@ -25,13 +26,25 @@ impl Default for RoomStatus {
 pub struct Room {
     pub id: u32,
     pub users: Vec<Arc<RwLock<User>>>,
+    /// watching without a seat, not counted against max player count
+    pub spectators: Vec<Arc<RwLock<User>>>,
     game_configs: GameConfigurations,
-    pub cur_game: Option<Game>,
+    /// the last match stays here until the next one starts
+    pub cur_match: Option<Arc<RwLock<Match>>>,
+    pub cur_game: Option<Arc<RwLock<Game>>>,
     pub status: RoomStatus,
     pub detailed_info_change_watch: WatcherWrapper<RoomDetailedInfo>,
 }
 
 impl Room {
+    /// nobody is notified, the room is not watched yet
+    pub fn with_game_configs(game_configs: GameConfigurations) -> Self {
+        Self {
+            game_configs,
+            ..Default::default()
+        }
+    }
+
     pub fn owner(&self) -> Arc<RwLock<User>> {
         self.users[0].clone()
     }
@@ -83,7 +96,9 @@ pub struct RoomDetailedInfo {
     pub id: u32,
     pub status: RoomStatus,
     pub user_in_room_infos: Vec<UserInRoomInfo>,
+    pub spectator_infos: Vec<UserInRoomInfo>,
     pub config: GameConfigurations,
+    pub match_summary: Option<MatchSummary>,
 }
 
 // user information needed to render the room page
@@ -108,11 +123,17 @@ impl From<&Room> for RoomDetailedInfo {
             id: value.id,
             status: value.status.clone(),
             config: value.game_configs.clone(),
+            match_summary: value.cur_match.as_ref().map(|m| m.read().summary()),
             user_in_room_infos: value
                 .users
                 .iter()
                 .map(|u| u.read().deref().into())
                 .collect(),
+            spectator_infos: value
+                .spectators
+                .iter()
+                .map(|u| u.read().deref().into())
+                .collect(),
         }
     }
 }
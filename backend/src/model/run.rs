@@ -0,0 +1,248 @@
+use crate::model::configs::RunConfigurations;
+use crate::model::game::Game;
+use crate::model::shop::Shop;
+use crate::model::tool::ToolKind;
+use anyhow::{anyhow, Error};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+
+/// run score of a won hand, before relics
+const WIN_SCORE: u32 = 10;
+
+/// kept by a seat for the rest of the run once chosen
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Relic {
+    /// the tool at the start of every hand, only offered when tools are enabled
+    Toolbox(ToolKind),
+    /// more run score for every hand won
+    Trophy { bonus: u32 },
+    /// one more hand can be lost before the seat is defeated
+    ExtraLife,
+    /// one run score for every 10 points won in tricks
+    Collector,
+}
+
+impl Relic {
+    pub(crate) fn all(tools_enabled: bool) -> Vec<Relic> {
+        let mut relics = vec![
+            Relic::Trophy { bonus: 5 },
+            Relic::ExtraLife,
+            Relic::Collector,
+        ];
+        if tools_enabled {
+            relics.extend([
+                Relic::Toolbox(ToolKind::ExchangeLocation),
+                Relic::Toolbox(ToolKind::ReversePlayDirection),
+                Relic::Toolbox(ToolKind::PeekBottomCard),
+            ]);
+        }
+        relics
+    }
+
+    /// the tool granted on every hand
+    pub fn tool(&self) -> Option<ToolKind> {
+        match self {
+            Relic::Toolbox(tool) => Some(*tool),
+            _ => None,
+        }
+    }
+
+    /// called on every new hand before it is dealt
+    pub fn on_hand_start(&self, seat: usize, game: &mut Game) {
+        if let Some(tool) = self.tool() {
+            game.grant_tool(seat, tool);
+        }
+    }
+
+    /// run score added on top of `WIN_SCORE` for a finished hand
+    pub fn hand_score(&self, won: bool, points: u32) -> u32 {
+        match self {
+            Relic::Trophy { bonus } if won => *bonus,
+            Relic::Collector => points / 10,
+            _ => 0,
+        }
+    }
+
+    pub fn extra_lives(&self) -> u32 {
+        match self {
+            Relic::ExtraLife => 1,
+            _ => 0,
+        }
+    }
+}
+
+/// progress of a run, everything is by seat
+pub struct RunState {
+    configs: RunConfigurations,
+    tools_enabled: bool,
+    rng: StdRng,
+    pub relics: Vec<Vec<Relic>>,
+    /// relics to choose one from before the next hand, empty once chosen
+    pub offers: Vec<Vec<Relic>>,
+    pub lives_lost: Vec<u32>,
+    pub scores: Vec<u32>,
+    /// spent in the shop between hands
+    pub coins: Vec<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RunSummary {
+    pub relics: Vec<Vec<Relic>>,
+    pub offers: Vec<Vec<Relic>>,
+    pub lives_left: Vec<u32>,
+    /// final once the run is over, this is what goes to the leaderboard
+    pub scores: Vec<u32>,
+    pub coins: Vec<u32>,
+    /// seats without lives left, the run is over once there is any
+    pub defeated: Vec<usize>,
+}
+
+impl RunState {
+    pub fn new(
+        configs: RunConfigurations,
+        tools_enabled: bool,
+        rng: StdRng,
+        seat_count: usize,
+    ) -> Self {
+        Self {
+            configs,
+            tools_enabled,
+            rng,
+            relics: vec![vec![]; seat_count],
+            offers: vec![vec![]; seat_count],
+            lives_lost: vec![0; seat_count],
+            scores: vec![0; seat_count],
+            coins: vec![0; seat_count],
+        }
+    }
+
+    pub fn lives_left(&self, seat: usize) -> u32 {
+        let lives = self.configs.lives
+            + self.relics[seat]
+                .iter()
+                .map(|r| r.extra_lives())
+                .sum::<u32>();
+        lives.saturating_sub(self.lives_lost[seat])
+    }
+
+    pub fn defeated(&self) -> Vec<usize> {
+        (0..self.scores.len())
+            .filter(|seat| self.lives_left(*seat) == 0)
+            .collect()
+    }
+
+    pub fn is_over(&self) -> bool {
+        !self.defeated().is_empty()
+    }
+
+    /// score the hand for every seat, losers lose a life and everyone earns coins
+    pub fn record_hand(&mut self, won_by_seat: &[bool], points_by_seat: &[u32]) {
+        for (seat, won) in won_by_seat.iter().enumerate() {
+            let bonus: u32 = self.relics[seat]
+                .iter()
+                .map(|r| r.hand_score(*won, points_by_seat[seat]))
+                .sum();
+            self.scores[seat] += if *won { WIN_SCORE } else { 0 } + bonus;
+            if !won {
+                self.lives_lost[seat] += 1;
+            }
+            if let Some(shop_configs) = self.configs.shop.as_ref() {
+                self.coins[seat] += points_by_seat[seat] / 10 * shop_configs.coins_per_ten_points
+                    + if *won { shop_configs.coins_per_win } else { 0 };
+            }
+        }
+    }
+
+    /// random relics for every seat, the ones a seat already has are not offered again
+    pub fn make_offers(&mut self) {
+        let pool = Relic::all(self.tools_enabled);
+        for seat in 0..self.offers.len() {
+            let candidates: Vec<Relic> = pool
+                .iter()
+                .filter(|r| !self.relics[seat].contains(r))
+                .cloned()
+                .collect();
+            self.offers[seat] = candidates
+                .choose_multiple(&mut self.rng, self.configs.offer_count)
+                .cloned()
+                .collect();
+        }
+    }
+
+    pub fn choose(&mut self, seat: usize, index: usize) -> Result<Relic, Error> {
+        let offers = self
+            .offers
+            .get_mut(seat)
+            .ok_or(anyhow!("no seat {}", seat))?;
+        if index >= offers.len() {
+            return Err(anyhow!("no relic offered at {}", index));
+        }
+        let relic = offers[index];
+        offers.clear();
+        self.relics[seat].push(relic);
+        Ok(relic)
+    }
+
+    pub fn all_chosen(&self) -> bool {
+        self.offers.iter().all(|o| o.is_empty())
+    }
+
+    /// whoever did not choose in time gets the first offer
+    pub fn choose_for_the_rest(&mut self) {
+        for seat in 0..self.offers.len() {
+            if !self.offers[seat].is_empty() {
+                let _ = self.choose(seat, 0);
+            }
+        }
+    }
+
+    /// the shop before the next hand, offers depend on the seed of the hand
+    pub fn open_shop(&self, game: &mut Game) {
+        let Some(shop_configs) = self.configs.shop.clone() else {
+            return;
+        };
+        let shop = Shop::new(
+            shop_configs,
+            game.seed,
+            self.tools_enabled,
+            self.coins.clone(),
+            self.relics.clone(),
+        );
+        game.open_shop(shop);
+    }
+
+    /// coins left and relics bought are kept for the rest of the run, from the moment they are bought
+    /// syncing the same shop again only adds what was bought since
+    pub fn sync_shop(&mut self, shop: &Shop) {
+        self.coins = shop.coins.clone();
+        for (relics, bought) in self.relics.iter_mut().zip(shop.bought_relics.iter()) {
+            for relic in bought {
+                if !relics.contains(relic) {
+                    relics.push(*relic);
+                }
+            }
+        }
+    }
+
+    pub fn on_hand_start(&self, game: &mut Game) {
+        for (seat, relics) in self.relics.iter().enumerate() {
+            for relic in relics {
+                relic.on_hand_start(seat, game);
+            }
+        }
+    }
+
+    pub fn summary(&self) -> RunSummary {
+        RunSummary {
+            relics: self.relics.clone(),
+            offers: self.offers.clone(),
+            lives_left: (0..self.scores.len())
+                .map(|seat| self.lives_left(seat))
+                .collect(),
+            scores: self.scores.clone(),
+            coins: self.coins.clone(),
+            defeated: self.defeated(),
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use crate::model::configs::BasicConfigurations;
+use crate::model::poker::{CardCopy, DECK_CARD_COUNT};
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+/// bottom cards left after dealing should never be less than this
+const MIN_BOTTOM_SIZE: usize = 6;
+
+#[test]
+fn test_shoe() {
+    let shoe = Shoe::new(4);
+    assert_eq!(shoe.len(), 216);
+    let spade_aces: Vec<&CardCopy> = shoe
+        .copies()
+        .iter()
+        .filter(|c| c.intrinsic_id() == 39)
+        .collect();
+    assert_eq!(spade_aces.len(), 4);
+    assert_ne!(spade_aces[0], spade_aces[1]);
+    assert_eq!(
+        spade_aces[0].card().intrinsic_id,
+        spade_aces[1].card().intrinsic_id
+    );
+}
+
+#[test]
+fn test_shoe_deal() {
+    let mut shoe = Shoe::new(4);
+    shoe.shuffle(&mut rand::thread_rng());
+    let bottom_size = shoe.default_bottom_size(6);
+    assert_eq!(bottom_size, 6);
+    let deal = shoe.deal(6, bottom_size);
+    assert_eq!(deal.bottom.len(), 6);
+    assert!(deal.hands.iter().all(|h| h.len() == 35));
+
+    let shoe = Shoe::new(4);
+    let mut configs = BasicConfigurations {
+        bottom_size: Some(12),
+        ..Default::default()
+    };
+    assert_eq!(shoe.bottom_size_of(&configs), 12);
+    // 216 - 10 cannot be split among 6 players
+    configs.bottom_size = Some(10);
+    assert_eq!(shoe.bottom_size_of(&configs), 6);
+}
+
+/// several decks mixed together, every card dealt in a game comes from here
+pub struct Shoe {
+    copies: Vec<CardCopy>,
+}
+
+/// hands are indexed by seat
+pub struct Deal {
+    pub hands: Vec<Vec<CardCopy>>,
+    pub bottom: Vec<CardCopy>,
+}
+
+impl Shoe {
+    pub fn new(deck_size: u8) -> Self {
+        let copies = (0..deck_size as u32)
+            .flat_map(|deck_index| {
+                (0..DECK_CARD_COUNT)
+                    .map(move |intrinsic_id| CardCopy::new(deck_index, intrinsic_id))
+            })
+            .collect();
+        Self { copies }
+    }
+
+    pub fn of_configs(configs: &BasicConfigurations) -> Self {
+        Self::new(configs.deck_size)
+    }
+
+    pub fn len(&self) -> usize {
+        self.copies.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.copies.is_empty()
+    }
+
+    pub fn copies(&self) -> &Vec<CardCopy> {
+        &self.copies
+    }
+
+    pub fn shuffle(&mut self, rng: &mut impl Rng) {
+        self.copies.shuffle(rng);
+    }
+
+    /// smallest bottom, but at least `MIN_BOTTOM_SIZE`, that lets every player get the same count
+    pub fn default_bottom_size(&self, player_count: usize) -> usize {
+        let total = self.len();
+        let mut bottom_size = total % player_count;
+        while bottom_size < MIN_BOTTOM_SIZE && bottom_size + player_count <= total {
+            bottom_size += player_count;
+        }
+        bottom_size
+    }
+
+    /// every player must get the same count, and at least one card
+    pub fn is_valid_bottom_size(&self, player_count: usize, bottom_size: usize) -> bool {
+        bottom_size < self.len() && (self.len() - bottom_size).is_multiple_of(player_count)
+    }
+
+    /// the configured bottom size if valid, otherwise the default one
+    pub fn bottom_size_of(&self, configs: &BasicConfigurations) -> usize {
+        let player_count = configs.max_player_count as usize;
+        match configs.bottom_size {
+            Some(size) if self.is_valid_bottom_size(player_count, size) => size,
+            _ => self.default_bottom_size(player_count),
+        }
+    }
+
+    /// cards are dealt one by one in seat order, the last `bottom_size` cards are left as bottom
+    pub fn deal(mut self, player_count: usize, bottom_size: usize) -> Deal {
+        let bottom = self.copies.split_off(self.copies.len() - bottom_size);
+        let mut hands = vec![vec![]; player_count];
+        for (i, copy) in self.copies.into_iter().enumerate() {
+            hands[i % player_count].push(copy);
+        }
+        Deal { hands, bottom }
+    }
+}
@@ -0,0 +1,184 @@
+use crate::model::configs::ShopConfigurations;
+use crate::model::run::Relic;
+use crate::model::tool::ToolKind;
+use anyhow::{anyhow, Error};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+
+#[test]
+fn test_shop() {
+    let configs = ShopConfigurations::default();
+    let new_shop = || Shop::new(configs.clone(), 42, true, vec![10, 0], vec![vec![], vec![]]);
+    let mut shop = new_shop();
+    assert_eq!(shop.items, new_shop().items);
+    assert_eq!(shop.items[0].len(), configs.item_count);
+    let price = shop.items[0][0].price;
+    let goods = shop.buy(0, 0).unwrap();
+    assert_eq!(shop.coins[0], 10 - price);
+    assert!(shop.buy(0, 0).is_err());
+    assert!(shop.buy(1, 0).is_err());
+    if let Goods::Relic(relic) = goods {
+        assert_eq!(shop.bought_relics[0], vec![relic]);
+    }
+    let before = shop.items[0].clone();
+    shop.reroll(0).unwrap();
+    assert_eq!(shop.coins[0], 10 - price - configs.reroll_price);
+    assert_ne!(shop.items[0], before);
+    assert!(shop.reroll(1).is_err());
+    shop.leave(0);
+    shop.leave(1);
+    assert!(shop.everyone_left());
+}
+
+/// what can be bought, tools are for the coming hand, relics for the rest of the run
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Goods {
+    Tool(ToolKind),
+    Relic(Relic),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ShopItem {
+    pub goods: Goods,
+    pub price: u32,
+    pub sold: bool,
+}
+
+/// opened before a hand of a run, everything is by seat
+/// the items only depend on the seed and rerolls, so a replayed shop offers the same
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Shop {
+    configs: ShopConfigurations,
+    seed: u64,
+    tools_enabled: bool,
+    rerolls: Vec<u32>,
+    /// relics owned before the shop opened, never offered again
+    owned_relics: Vec<Vec<Relic>>,
+    pub coins: Vec<u32>,
+    pub items: Vec<Vec<ShopItem>>,
+    pub bought_relics: Vec<Vec<Relic>>,
+    /// seats done shopping, the shop closes early once everyone is
+    pub left: Vec<bool>,
+}
+
+/// the shop as one seat sees it
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ShopView {
+    pub coins: u32,
+    pub items: Vec<ShopItem>,
+    pub reroll_price: u32,
+    pub left: bool,
+}
+
+impl Shop {
+    pub fn new(
+        configs: ShopConfigurations,
+        seed: u64,
+        tools_enabled: bool,
+        coins: Vec<u32>,
+        owned_relics: Vec<Vec<Relic>>,
+    ) -> Self {
+        let seat_count = coins.len();
+        let mut shop = Self {
+            configs,
+            seed,
+            tools_enabled,
+            rerolls: vec![0; seat_count],
+            owned_relics,
+            coins,
+            items: vec![vec![]; seat_count],
+            bought_relics: vec![vec![]; seat_count],
+            left: vec![false; seat_count],
+        };
+        for seat in 0..seat_count {
+            shop.restock(seat);
+        }
+        shop
+    }
+
+    pub fn configs(&self) -> &ShopConfigurations {
+        &self.configs
+    }
+
+    fn restock(&mut self, seat: usize) {
+        let mut goods: Vec<Goods> = Relic::all(self.tools_enabled)
+            .into_iter()
+            .filter(|r| {
+                !self.owned_relics[seat].contains(r) && !self.bought_relics[seat].contains(r)
+            })
+            .map(Goods::Relic)
+            .collect();
+        if self.tools_enabled {
+            goods.extend([
+                Goods::Tool(ToolKind::ExchangeLocation),
+                Goods::Tool(ToolKind::ReversePlayDirection),
+                Goods::Tool(ToolKind::PeekBottomCard),
+            ]);
+        }
+        let seed = self.seed ^ ((seat as u64) << 32) ^ self.rerolls[seat] as u64;
+        let mut rng = StdRng::seed_from_u64(seed);
+        self.items[seat] = goods
+            .choose_multiple(&mut rng, self.configs.item_count)
+            .map(|goods| ShopItem {
+                goods: *goods,
+                price: match goods {
+                    Goods::Tool(_) => self.configs.tool_price,
+                    Goods::Relic(_) => self.configs.relic_price,
+                },
+                sold: false,
+            })
+            .collect();
+    }
+
+    fn pay(&mut self, seat: usize, price: u32) -> Result<(), Error> {
+        if self.left[seat] {
+            return Err(anyhow!("already left the shop"));
+        }
+        if self.coins[seat] < price {
+            return Err(anyhow!("need {} coins", price));
+        }
+        self.coins[seat] -= price;
+        Ok(())
+    }
+
+    pub fn buy(&mut self, seat: usize, index: usize) -> Result<Goods, Error> {
+        let item = self.items[seat]
+            .get(index)
+            .filter(|item| !item.sold)
+            .cloned()
+            .ok_or(anyhow!("nothing to buy at {}", index))?;
+        self.pay(seat, item.price)?;
+        self.items[seat][index].sold = true;
+        if let Goods::Relic(relic) = item.goods {
+            self.bought_relics[seat].push(relic);
+        }
+        Ok(item.goods)
+    }
+
+    /// a new selection for the seat, unsold items are gone
+    pub fn reroll(&mut self, seat: usize) -> Result<(), Error> {
+        self.pay(seat, self.configs.reroll_price)?;
+        self.rerolls[seat] += 1;
+        self.restock(seat);
+        Ok(())
+    }
+
+    pub fn leave(&mut self, seat: usize) {
+        self.left[seat] = true;
+    }
+
+    pub fn everyone_left(&self) -> bool {
+        self.left.iter().all(|left| *left)
+    }
+
+    pub fn view_of(&self, seat: usize) -> ShopView {
+        ShopView {
+            coins: self.coins[seat],
+            items: self.items[seat].clone(),
+            reroll_price: self.configs.reroll_price,
+            left: self.left[seat],
+        }
+    }
+}
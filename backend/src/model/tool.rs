@@ -1,7 +1,148 @@
-use crate::model::game::Game;
+use crate::model::game::{Game, GamePhase};
+use crate::model::poker::CardCopy;
+use anyhow::{anyhow, Error};
+use serde::{Deserialize, Serialize};
 
+#[test]
+fn test_use_tools() {
+    use crate::model::configs::GameConfigurations;
+    use crate::model::game::{test_users, GameAction};
+    let mut configs = GameConfigurations::default();
+    configs.basic_configs.max_player_count = 4;
+    configs.play_configs.tools_enabled = true;
+    let mut game = Game::with_seed(test_users(4), configs, 42);
+    game.deal();
+    game.enter_phase(GamePhase::DeclaringPrime);
+    game.resolve_prime();
+    game.pick_up_bottom();
+    game.enter_phase(GamePhase::ExchangingBottom);
+    assert!(game.use_tool(0, ToolUse::ReversePlayDirection).is_err());
+    let buried = game.players()[0].hand[..game.bottom_size].to_vec();
+    game.apply_action(0, GameAction::BuryBottom(buried.clone()))
+        .unwrap();
+    assert_eq!(game.players()[1].tools.len(), 3);
+    // only the leader, before leading
+    assert!(game.use_tool(1, ToolUse::ReversePlayDirection).is_err());
+    game.use_tool(0, ToolUse::ReversePlayDirection).unwrap();
+    assert_eq!(game.turn_order, vec![3, 2, 1, 0]);
+    assert!(game.use_tool(0, ToolUse::ReversePlayDirection).is_err());
+    game.use_tool(0, ToolUse::ExchangeLocation { with_seat: 2 })
+        .unwrap();
+    assert_eq!(game.turn_order, vec![3, 0, 1, 2]);
+    assert_eq!(
+        game.use_tool(0, ToolUse::PeekBottomCard { index: 1 })
+            .unwrap(),
+        ToolOutcome::Peeked(buried[1])
+    );
+    let lead = vec![game.players()[0].hand[0]];
+    game.play_cards(0, lead).unwrap();
+    assert_eq!(game.cur_turn, 1);
+}
+
+/// tools a player can hold, each one is used once
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ToolKind {
+    ExchangeLocation,
+    ReversePlayDirection,
+    PeekBottomCard,
+}
+
+/// when tools are handed out, a kind nobody is granted is never in play
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum ToolGrant {
+    /// every player gets one once dealing starts
+    EveryPlayer(ToolKind),
+    /// whoever wins a trick worth at least `min_points`
+    TrickWinner { tool: ToolKind, min_points: u32 },
+}
+
+/// what a player asks to use, with the target of the tool
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum ToolUse {
+    ExchangeLocation { with_seat: usize },
+    ReversePlayDirection,
+    PeekBottomCard { index: usize },
+}
+
+/// reply to a used tool, only the user of the tool gets it
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum ToolOutcome {
+    Used,
+    Peeked(CardCopy),
+}
+
+/// tools are used by the leader of a trick before leading, see `Game::use_tool`
 pub trait Tool {
-    fn apply(self, game: &mut Game);
+    fn apply(self, game: &mut Game, seat: usize) -> Result<ToolOutcome, Error>;
 }
 
-pub struct ExchangeLocationCard {}
+/// swap places at the table with another player, seats and sides stay the same
+pub struct ExchangeLocationCard {
+    pub with_seat: usize,
+}
+
+/// the next players follow in the opposite direction
+pub struct ReversePlayDirectionCard {}
+
+/// look at one of the buried bottom cards
+pub struct PeekBottomCard {
+    pub index: usize,
+}
+
+impl Tool for ExchangeLocationCard {
+    fn apply(self, game: &mut Game, seat: usize) -> Result<ToolOutcome, Error> {
+        if self.with_seat == seat || self.with_seat >= game.players().len() {
+            return Err(anyhow!(
+                "cannot exchange location with seat {}",
+                self.with_seat
+            ));
+        }
+        let position = |s: usize| game.turn_order.iter().position(|o| *o == s).unwrap();
+        let (a, b) = (position(seat), position(self.with_seat));
+        game.turn_order.swap(a, b);
+        Ok(ToolOutcome::Used)
+    }
+}
+
+impl Tool for ReversePlayDirectionCard {
+    fn apply(self, game: &mut Game, _: usize) -> Result<ToolOutcome, Error> {
+        game.turn_order.reverse();
+        Ok(ToolOutcome::Used)
+    }
+}
+
+impl Tool for PeekBottomCard {
+    fn apply(self, game: &mut Game, seat: usize) -> Result<ToolOutcome, Error> {
+        let card = game.peek_bottom(seat, self.index)?;
+        Ok(ToolOutcome::Peeked(card))
+    }
+}
+
+impl ToolUse {
+    pub fn kind(&self) -> ToolKind {
+        match self {
+            ToolUse::ExchangeLocation { .. } => ToolKind::ExchangeLocation,
+            ToolUse::ReversePlayDirection => ToolKind::ReversePlayDirection,
+            ToolUse::PeekBottomCard { .. } => ToolKind::PeekBottomCard,
+        }
+    }
+
+    /// checks of the window and the inventory are done by the game
+    pub(crate) fn apply(self, game: &mut Game, seat: usize) -> Result<ToolOutcome, Error> {
+        match self {
+            ToolUse::ExchangeLocation { with_seat } => {
+                ExchangeLocationCard { with_seat }.apply(game, seat)
+            }
+            ToolUse::ReversePlayDirection => ReversePlayDirectionCard {}.apply(game, seat),
+            ToolUse::PeekBottomCard { index } => PeekBottomCard { index }.apply(game, seat),
+        }
+    }
+}
+
+/// whether a player can use tools right now, only the leader before the trick is led
+pub fn in_tool_window(game: &Game, seat: usize) -> bool {
+    game.configurable_rules().play_configs.tools_enabled
+        && game.phase == GamePhase::Playing
+        && game.cur_turn == seat
+        && game.cur_trick().is_some_and(|t| t.plays.is_empty())
+}
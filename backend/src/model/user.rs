@@ -1,4 +1,5 @@
 use crate::model::configs::GameConfigurations;
+use crate::model::preset::ConfigPreset;
 use baodatui_macro::ID;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
@@ -11,7 +12,17 @@ pub struct User {
     pub uuid: String,
     pub login_timestamp: u64,
     pub prepared: bool,
+    /// rooms created by the user start with these, set by applying a preset
     pub preferred_game_config: Option<GameConfigurations>,
+    /// presets saved by the user, see `model::preset`
+    #[serde(default)]
+    pub saved_presets: Vec<ConfigPreset>,
+    /// highest final score of the runs played, for the leaderboard
+    #[serde(default)]
+    pub best_run_score: u32,
+    /// open connections, a user reconnecting with the same uuid gets the same user back
+    #[serde(skip)]
+    pub connection_count: u32,
 }
 
 impl Default for User {
@@ -24,11 +35,20 @@ impl Default for User {
             uuid,
             login_timestamp: 0,
             preferred_game_config: None,
+            saved_presets: vec![],
             prepared: false,
+            best_run_score: 0,
+            connection_count: 0,
         }
     }
 }
 
+impl User {
+    pub fn is_online(&self) -> bool {
+        self.connection_count > 0
+    }
+}
+
 #[test]
 fn test_user() {
     let user = User::default();
@@ -1,11 +1,17 @@
 use crate::ext::IntoResult;
+use crate::global::room_manager::room_manager;
 use crate::global::rsocket_manager::rsocket_manager;
+use crate::global::settings::system_settings;
+use crate::global::user_manager::user_manager;
 use async_trait::async_trait;
 use futures_util::StreamExt;
 use rsocket_rust::prelude::{Flux, Payload, RSocket};
 use rsocket_rust::stream;
 use serde_json::Value;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::spawn;
+use tokio::time::sleep;
 
 // per user connection
 #[derive(Clone)]
@@ -14,6 +20,34 @@ pub struct ServerRSocket {
     pub user_id: u32,
 }
 
+/// the user is online until pushing to the client fails, the server cannot tell otherwise
+/// since the client socket and our responder keep each other alive
+pub fn watch_connection(user_id: u32, client_rsocket: Arc<dyn RSocket>) {
+    update_connection_count(user_id, |count| count + 1);
+    spawn(async move {
+        loop {
+            let interval = system_settings().connection_heartbeat_interval;
+            sleep(Duration::from_millis(interval)).await;
+            let heartbeat = Payload::builder().build();
+            if client_rsocket.metadata_push(heartbeat).await.is_err() {
+                break;
+            }
+        }
+        update_connection_count(user_id, |count| count.saturating_sub(1));
+    });
+}
+
+fn update_connection_count(user_id: u32, f: impl FnOnce(u32) -> u32) {
+    if let Some(user) = user_manager().get(user_id) {
+        let mut user = user.write();
+        user.connection_count = f(user.connection_count);
+    }
+    // others in the same game see who is online
+    if let Ok(game) = room_manager().find_game_by_user_id(user_id) {
+        game.read().notify_state_changed();
+    }
+}
+
 #[async_trait]
 impl RSocket for ServerRSocket {
     async fn metadata_push(&self, req: Payload) -> anyhow::Result<()> {
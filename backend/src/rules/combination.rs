@@ -0,0 +1,136 @@
+use crate::model::baodatui_poker::{PowerRule, PrimeOrSub};
+use crate::model::poker::{cards, CardCopy};
+use crate::rules::group_identical;
+use serde::{Deserialize, Serialize};
+
+#[test]
+fn test_recognize() {
+    use crate::model::poker::Suit;
+    let rule = PowerRule::new(Some(Suit::SPADES), vec![2, 3, 5]);
+    let heart = |deck: u32, num: u32| CardCopy::of(deck, Suit::HEARTS, num);
+    let triple = [heart(0, 9), heart(1, 9), heart(2, 9)];
+    assert_eq!(
+        Combination::recognize(&triple, &rule).unwrap().shape,
+        Shape {
+            width: 3,
+            length: 1
+        }
+    );
+    // 4 and 6 are consecutive since 5 is always prime
+    let run = [heart(0, 4), heart(1, 4), heart(0, 6), heart(1, 6)];
+    assert_eq!(
+        Combination::recognize(&run, &rule).unwrap().shape,
+        Shape {
+            width: 2,
+            length: 2
+        }
+    );
+    assert!(
+        Combination::recognize(&[heart(0, 4), heart(1, 4), heart(0, 7), heart(1, 7)], &rule)
+            .is_none()
+    );
+    assert!(Combination::recognize(&[heart(0, 4), heart(1, 4), heart(0, 6)], &rule).is_none());
+
+    // prime pair of spade 2 and hearts 2 are consecutive
+    let spade_2 = CardCopy::of(0, Suit::SPADES, 2);
+    let spade_2_copy = CardCopy::of(1, Suit::SPADES, 2);
+    let prime_run = [heart(0, 2), heart(1, 2), spade_2, spade_2_copy];
+    let prime_run = Combination::recognize(&prime_run, &rule).unwrap();
+    assert!(prime_run.category.is_prime());
+    let led = Combination::recognize(&run, &rule).unwrap();
+    assert!(prime_run.beats(&led));
+    assert!(!led.beats(&prime_run));
+    let bigger = Combination::recognize(
+        &[heart(0, 10), heart(1, 10), heart(0, 11), heart(1, 11)],
+        &rule,
+    )
+    .unwrap();
+    assert!(bigger.beats(&led));
+}
+
+/// `length` consecutive groups of `width` identical cards,
+/// single is 1x1, pair 2x1, triple 3x1, quad 4x1, consecutive pairs 2xN
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Shape {
+    pub width: usize,
+    pub length: usize,
+}
+
+impl Shape {
+    pub fn card_count(&self) -> usize {
+        self.width * self.length
+    }
+}
+
+/// cards that can be led together as one unit
+#[derive(Debug, Clone)]
+pub struct Combination {
+    pub shape: Shape,
+    pub category: PrimeOrSub,
+    /// power of the strongest group
+    pub rank: i32,
+    pub cards: Vec<CardCopy>,
+}
+
+impl Combination {
+    /// None if the cards are not a single combination
+    pub fn recognize(cards: &[CardCopy], rule: &PowerRule) -> Option<Self> {
+        if cards.is_empty() {
+            return None;
+        }
+        let category = rule.classify(cards[0].card());
+        if cards.iter().any(|c| rule.classify(c.card()) != category) {
+            return None;
+        }
+        let mut groups = group_identical(cards);
+        let width = groups[0].len();
+        if groups.iter().any(|g| g.len() != width) {
+            return None;
+        }
+        groups.sort_by_key(|g| rule.power(g[0].card()));
+        let length = groups.len();
+        if length > 1 {
+            if width < 2 {
+                return None;
+            }
+            let levels: Vec<usize> = groups.iter().map(|g| level_of(&g[0], rule)).collect();
+            if levels.windows(2).any(|w| w[1] != w[0] + 1) {
+                return None;
+            }
+        }
+        Some(Self {
+            shape: Shape { width, length },
+            category,
+            rank: rule.power(groups[length - 1][0].card()),
+            cards: cards.to_vec(),
+        })
+    }
+
+    /// whether this combination, played later in a trick, takes it from `best`
+    /// only the same shape can beat, prime beats sub, ties keep the earlier play
+    pub fn beats(&self, best: &Combination) -> bool {
+        if self.shape != best.shape {
+            return false;
+        }
+        if self.category == best.category {
+            return self.rank > best.rank;
+        }
+        self.category.is_prime() && best.category.is_sub()
+    }
+}
+
+/// position of the card among the distinct powers of its prime or sub suit,
+/// two groups are consecutive when their levels are adjacent
+pub(crate) fn level_of(card: &CardCopy, rule: &PowerRule) -> usize {
+    let category = rule.classify(card.card());
+    let mut powers: Vec<i32> = cards()
+        .cards
+        .iter()
+        .filter(|c| rule.classify(c) == category)
+        .map(|c| rule.power(c))
+        .collect();
+    powers.sort();
+    powers.dedup();
+    let power = rule.power(card.card());
+    powers.iter().position(|p| *p == power).unwrap()
+}
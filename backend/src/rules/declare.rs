@@ -0,0 +1,70 @@
+use crate::model::poker::{CardCopy, Suit};
+use anyhow::{anyhow, Error};
+use serde::{Deserialize, Serialize};
+
+#[test]
+fn test_declaration_override() {
+    let always_prime_numbers = [2, 3, 5];
+    let heart_2 = |deck: u32| CardCopy::of(deck, Suit::HEARTS, 2);
+    let club_3 = |deck: u32| CardCopy::of(deck, Suit::CLUBS, 3);
+    let single = PrimeDeclaration::new(0, vec![heart_2(0)], &always_prime_numbers).unwrap();
+    assert_eq!(single.prime_suit(), Some(Suit::HEARTS));
+    let pair = PrimeDeclaration::new(1, vec![club_3(0), club_3(1)], &always_prime_numbers).unwrap();
+    assert!(pair.overrides(&single));
+    assert!(!single.overrides(&pair));
+    let same =
+        PrimeDeclaration::new(2, vec![heart_2(0), heart_2(1)], &always_prime_numbers).unwrap();
+    assert!(!same.overrides(&pair));
+    // jokers, mixed cards and numbers not always prime cannot declare
+    let jokers = vec![CardCopy::new(0, 52), CardCopy::new(1, 52)];
+    assert!(PrimeDeclaration::new(3, jokers, &always_prime_numbers).is_err());
+    assert!(PrimeDeclaration::new(0, vec![heart_2(0), club_3(0)], &always_prime_numbers).is_err());
+    assert!(PrimeDeclaration::new(
+        0,
+        vec![CardCopy::of(0, Suit::HEARTS, 7)],
+        &always_prime_numbers
+    )
+    .is_err());
+}
+
+/// identical cards shown to claim the prime suit, every hand has one
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PrimeDeclaration {
+    pub seat: usize,
+    pub cards: Vec<CardCopy>,
+}
+
+impl PrimeDeclaration {
+    /// only copies of one always prime number of a suit
+    pub fn new(
+        seat: usize,
+        cards: Vec<CardCopy>,
+        always_prime_numbers: &[u32],
+    ) -> Result<Self, Error> {
+        let first = cards.first().ok_or(anyhow!("no card to declare with"))?;
+        if cards
+            .iter()
+            .any(|c| c.intrinsic_id() != first.intrinsic_id())
+        {
+            return Err(anyhow!("must declare with identical cards"));
+        }
+        match first.card().numeric_card_num {
+            None => return Err(anyhow!("jokers cannot declare the prime suit")),
+            Some(num) if !always_prime_numbers.contains(&num) => {
+                return Err(anyhow!("{} cannot declare the prime suit", num))
+            }
+            _ => {}
+        }
+        Ok(Self { seat, cards })
+    }
+
+    /// always Some, declarations are never made with jokers
+    pub fn prime_suit(&self) -> Option<Suit> {
+        self.cards[0].card().suit
+    }
+
+    /// only more copies take over
+    pub fn overrides(&self, current: &PrimeDeclaration) -> bool {
+        self.cards.len() > current.cards.len()
+    }
+}
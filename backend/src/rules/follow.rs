@@ -0,0 +1,302 @@
+use crate::model::baodatui_poker::{score_of_numeric_number, PowerRule, PrimeOrSub};
+use crate::model::poker::{CardCopy, Suit};
+use crate::rules::combination::Combination;
+use crate::rules::{contains_all, group_identical, pair_count};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+
+#[test]
+fn test_follow() {
+    let rule = PowerRule::new(Some(Suit::SPADES), vec![2, 3, 5]);
+    let heart_4 = CardCopy::of(0, Suit::HEARTS, 4);
+    let heart_4_copy = CardCopy::of(1, Suit::HEARTS, 4);
+    let heart_9 = CardCopy::of(0, Suit::HEARTS, 9);
+    let club_7 = CardCopy::of(0, Suit::CLUBS, 7);
+    let hand = vec![heart_4, heart_4_copy, heart_9, club_7];
+    let lead = vec![
+        CardCopy::of(0, Suit::HEARTS, 6),
+        CardCopy::of(1, Suit::HEARTS, 6),
+    ];
+
+    assert_eq!(
+        check_follow(&hand, &lead, &[heart_9, club_7], &rule),
+        Err(IllegalPlay::MustFollowSubSuit(Suit::HEARTS))
+    );
+    assert_eq!(
+        check_follow(&hand, &lead, &[heart_4, heart_9], &rule),
+        Err(IllegalPlay::MustFollowPairs { pairs: 1 })
+    );
+    assert_eq!(
+        check_follow(&hand, &lead, &[heart_4, heart_4_copy], &rule),
+        Ok(())
+    );
+    let legal = legal_follows(&hand, &lead, &rule);
+    assert_eq!(legal.minimal(&rule), vec![heart_4, heart_4_copy]);
+
+    // only one heart left, it must be played
+    let hand = vec![heart_9, club_7, CardCopy::of(0, Suit::CLUBS, 8)];
+    assert_eq!(
+        check_follow(
+            &hand,
+            &lead,
+            &[club_7, CardCopy::of(0, Suit::CLUBS, 8)],
+            &rule
+        ),
+        Err(IllegalPlay::MustFollowSubSuit(Suit::HEARTS))
+    );
+    assert_eq!(
+        check_follow(&hand, &lead, &[heart_9, club_7], &rule),
+        Ok(())
+    );
+
+    // 4 identical hearts 9 are 2 pairs for a lead of 2 pairs
+    let lead = vec![
+        CardCopy::of(0, Suit::HEARTS, 7),
+        CardCopy::of(1, Suit::HEARTS, 7),
+        CardCopy::of(0, Suit::HEARTS, 8),
+        CardCopy::of(1, Suit::HEARTS, 8),
+    ];
+    let heart_9s: Vec<CardCopy> = (0..4).map(|i| CardCopy::of(i, Suit::HEARTS, 9)).collect();
+    let mut hand = heart_9s.clone();
+    hand.extend([heart_4, club_7]);
+    let legal = legal_follows(&hand, &lead, &rule);
+    let minimal = legal.minimal(&rule);
+    assert_eq!(check_follow(&hand, &lead, &minimal, &rule), Ok(()));
+    assert!(contains_all(&minimal, &heart_9s));
+    let searched = legal.search(&rule).unwrap();
+    assert_eq!(check_follow(&hand, &lead, &searched, &rule), Ok(()));
+}
+
+/// why a play is rejected, the client renders these
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IllegalPlay {
+    NotInHand,
+    WrongCardCount {
+        expected: usize,
+    },
+    /// cards of a lead must all be prime or all of the same sub suit
+    MixedLead,
+    /// without throws, lead must be a single, a tuple of identical cards or consecutive tuples
+    NotACombination,
+    MustFollowPrime,
+    MustFollowSubSuit(Suit),
+    /// must play this many pairs of the led prime or sub suit
+    MustFollowPairs {
+        pairs: usize,
+    },
+}
+
+impl IllegalPlay {
+    fn must_follow(led: PrimeOrSub) -> Self {
+        match led.sub_suit() {
+            None => IllegalPlay::MustFollowPrime,
+            Some(suit) => IllegalPlay::MustFollowSubSuit(suit),
+        }
+    }
+}
+
+impl Display for IllegalPlay {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IllegalPlay::NotInHand => write!(f, "cards not in hand"),
+            IllegalPlay::WrongCardCount { expected } => write!(f, "must play {} cards", expected),
+            IllegalPlay::MixedLead => write!(f, "lead cards must be of the same prime or sub suit"),
+            IllegalPlay::NotACombination => write!(f, "lead cards are not a combination"),
+            IllegalPlay::MustFollowPrime => write!(f, "must follow prime"),
+            IllegalPlay::MustFollowSubSuit(suit) => write!(f, "must follow {:?}", suit),
+            IllegalPlay::MustFollowPairs { pairs } => write!(f, "must follow {} pairs", pairs),
+        }
+    }
+}
+
+impl std::error::Error for IllegalPlay {}
+
+/// the set of legal follows: exactly `count` cards out of `playable`,
+/// including all of `required` and at least `pairs_required` pairs of the led prime or sub suit
+#[derive(Debug, Clone)]
+pub struct LegalPlays {
+    pub count: usize,
+    pub playable: Vec<CardCopy>,
+    pub required: Vec<CardCopy>,
+    pub pairs_required: usize,
+    led: PrimeOrSub,
+}
+
+pub fn legal_follows(hand: &[CardCopy], lead: &[CardCopy], rule: &PowerRule) -> LegalPlays {
+    let led = rule.classify(lead[0].card());
+    let led_cards: Vec<CardCopy> = hand
+        .iter()
+        .filter(|c| rule.classify(c.card()) == led)
+        .cloned()
+        .collect();
+    let pairs_required = pair_count(lead).min(pair_count(&led_cards));
+    if led_cards.len() >= lead.len() {
+        LegalPlays {
+            count: lead.len(),
+            playable: led_cards,
+            required: vec![],
+            pairs_required,
+            led,
+        }
+    } else {
+        LegalPlays {
+            count: lead.len(),
+            playable: hand.to_vec(),
+            required: led_cards,
+            pairs_required,
+            led,
+        }
+    }
+}
+
+impl LegalPlays {
+    pub fn check(&self, play: &[CardCopy], rule: &PowerRule) -> Result<(), IllegalPlay> {
+        if play.len() != self.count {
+            return Err(IllegalPlay::WrongCardCount {
+                expected: self.count,
+            });
+        }
+        if !contains_all(&self.playable, play) || !contains_all(play, &self.required) {
+            return Err(IllegalPlay::must_follow(self.led));
+        }
+        let led_cards: Vec<CardCopy> = play
+            .iter()
+            .filter(|c| rule.classify(c.card()) == self.led)
+            .cloned()
+            .collect();
+        if pair_count(&led_cards) < self.pairs_required {
+            return Err(IllegalPlay::MustFollowPairs {
+                pairs: self.pairs_required,
+            });
+        }
+        Ok(())
+    }
+
+    /// the weakest legal follow, used when a player does not choose by themselves
+    pub fn minimal(&self, rule: &PowerRule) -> Vec<CardCopy> {
+        let weakness = |c: &CardCopy| {
+            (
+                rule.power_in_trick(c.card(), self.led),
+                c.card().numeric_card_num.map_or(0, score_of_numeric_number),
+            )
+        };
+        let mut pool = self.playable.clone();
+        pool.sort_by_key(weakness);
+        let mut chosen: Vec<CardCopy> = vec![];
+        let led_cards: Vec<CardCopy> = pool
+            .iter()
+            .filter(|c| rule.classify(c.card()) == self.led)
+            .cloned()
+            .collect();
+        // a group of 4 identical cards is 2 pairs
+        let pairs = group_identical(&led_cards)
+            .into_iter()
+            .flat_map(|g| g.chunks_exact(2).map(|p| p.to_vec()).collect::<Vec<_>>())
+            .take(self.pairs_required);
+        for pair in pairs {
+            take(pair[0], &mut pool, &mut chosen);
+            take(pair[1], &mut pool, &mut chosen);
+        }
+        for card in self.required.iter() {
+            if !contains_all(&chosen, &[*card]) {
+                take(*card, &mut pool, &mut chosen);
+            }
+        }
+        while chosen.len() < self.count {
+            let card = pool[0];
+            take(card, &mut pool, &mut chosen);
+        }
+        chosen
+    }
+
+    /// any legal follow accepted by `check`, slower than `minimal` and only a fallback for it
+    pub fn search(&self, rule: &PowerRule) -> Option<Vec<CardCopy>> {
+        if self.required.len() > self.count {
+            return None;
+        }
+        // identical cards next to each other, so pairs are tried early
+        let mut rest: Vec<CardCopy> = self
+            .playable
+            .iter()
+            .filter(|c| !self.required.contains(c))
+            .cloned()
+            .collect();
+        rest.sort_by_key(|c| c.intrinsic_id());
+        let mut chosen = self.required.clone();
+        let mut budget = SEARCH_BUDGET;
+        self.search_from(rule, &rest, &mut chosen, &mut budget)
+            .then_some(chosen)
+    }
+
+    fn search_from(
+        &self,
+        rule: &PowerRule,
+        rest: &[CardCopy],
+        chosen: &mut Vec<CardCopy>,
+        budget: &mut u32,
+    ) -> bool {
+        if chosen.len() == self.count {
+            return self.check(chosen, rule).is_ok();
+        }
+        if *budget == 0 {
+            return false;
+        }
+        *budget -= 1;
+        let missing = self.count - chosen.len();
+        for (i, card) in rest.iter().enumerate() {
+            if rest.len() - i < missing {
+                break;
+            }
+            chosen.push(*card);
+            if self.search_from(rule, &rest[i + 1..], chosen, budget) {
+                return true;
+            }
+            chosen.pop();
+        }
+        false
+    }
+}
+
+/// how many partial plays `LegalPlays::search` tries before giving up
+const SEARCH_BUDGET: u32 = 100_000;
+
+fn take(card: CardCopy, pool: &mut Vec<CardCopy>, chosen: &mut Vec<CardCopy>) {
+    if let Some(i) = pool.iter().position(|c| *c == card) {
+        chosen.push(pool.remove(i));
+    }
+}
+
+/// a lead is either a single combination or, when `allow_throw`, a throw
+pub fn check_lead(
+    hand: &[CardCopy],
+    play: &[CardCopy],
+    rule: &PowerRule,
+    allow_throw: bool,
+) -> Result<(), IllegalPlay> {
+    if play.is_empty() {
+        return Err(IllegalPlay::WrongCardCount { expected: 1 });
+    }
+    if !contains_all(hand, play) {
+        return Err(IllegalPlay::NotInHand);
+    }
+    let led = rule.classify(play[0].card());
+    if play.iter().any(|c| rule.classify(c.card()) != led) {
+        return Err(IllegalPlay::MixedLead);
+    }
+    if !allow_throw && Combination::recognize(play, rule).is_none() {
+        return Err(IllegalPlay::NotACombination);
+    }
+    Ok(())
+}
+
+pub fn check_follow(
+    hand: &[CardCopy],
+    lead: &[CardCopy],
+    play: &[CardCopy],
+    rule: &PowerRule,
+) -> Result<(), IllegalPlay> {
+    if !contains_all(hand, play) {
+        return Err(IllegalPlay::NotInHand);
+    }
+    legal_follows(hand, lead, rule).check(play, rule)
+}
@@ -0,0 +1,42 @@
+pub mod combination;
+pub mod declare;
+pub mod follow;
+pub mod scoring;
+pub mod team;
+pub mod throw;
+
+use crate::model::poker::CardCopy;
+
+/// whether every card is in `hand`, cards are physical copies so each can only be used once
+pub fn contains_all(hand: &[CardCopy], cards: &[CardCopy]) -> bool {
+    let mut remaining = hand.to_vec();
+    cards
+        .iter()
+        .all(|card| match remaining.iter().position(|c| c == card) {
+            Some(i) => {
+                remaining.swap_remove(i);
+                true
+            }
+            None => false,
+        })
+}
+
+/// identical cards from different decks grouped together, in order of first appearance
+pub fn group_identical(cards: &[CardCopy]) -> Vec<Vec<CardCopy>> {
+    let mut groups: Vec<Vec<CardCopy>> = vec![];
+    for card in cards {
+        match groups
+            .iter_mut()
+            .find(|g| g[0].intrinsic_id() == card.intrinsic_id())
+        {
+            Some(group) => group.push(*card),
+            None => groups.push(vec![*card]),
+        }
+    }
+    groups
+}
+
+/// how many disjoint pairs of identical cards can be made
+pub fn pair_count(cards: &[CardCopy]) -> usize {
+    group_identical(cards).iter().map(|g| g.len() / 2).sum()
+}
@@ -0,0 +1,45 @@
+use crate::model::configs::ScoreConfigurations;
+use crate::model::poker::{Card, CardCopy};
+use serde::{Deserialize, Serialize};
+
+#[test]
+fn test_settle() {
+    let configs = ScoreConfigurations::default();
+    assert_eq!(settle(0, &configs), (Side::Declarer, 3));
+    assert_eq!(settle(150, &configs), (Side::Declarer, 1));
+    assert_eq!(settle(160, &configs), (Side::Defender, 0));
+    assert_eq!(settle(245, &configs), (Side::Defender, 2));
+}
+
+/// the declarer side owns the bottom, defenders collect points to take over
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Declarer,
+    Defender,
+}
+
+pub fn card_points(card: &Card, configs: &ScoreConfigurations) -> u32 {
+    match card.numeric_card_num {
+        Some(5) => configs.five_points,
+        Some(10) => configs.ten_points,
+        Some(13) => configs.king_points,
+        _ => 0,
+    }
+}
+
+pub fn points_of(cards: &[CardCopy], configs: &ScoreConfigurations) -> u32 {
+    cards.iter().map(|c| card_points(c.card(), configs)).sum()
+}
+
+/// which side wins the hand by `defender_points` and how many levels it goes up
+pub fn settle(defender_points: u32, configs: &ScoreConfigurations) -> (Side, u32) {
+    let step = configs.level_step.max(1);
+    let threshold = configs.defender_win_threshold;
+    if defender_points < threshold {
+        let levels = (threshold - defender_points).div_ceil(step);
+        (Side::Declarer, levels.min(configs.max_level_change))
+    } else {
+        let levels = (defender_points - threshold) / step;
+        (Side::Defender, levels.min(configs.max_level_change))
+    }
+}
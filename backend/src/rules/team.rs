@@ -0,0 +1,77 @@
+use crate::model::poker::CardCopy;
+use serde::{Deserialize, Serialize};
+
+#[test]
+fn test_called_card_reveals_partner() {
+    let mut teams = Teams::default();
+    // the second heart ace played
+    teams.call(vec![CalledCard {
+        intrinsic_id: 26,
+        nth: 2,
+    }]);
+    teams.on_play(1, &[CardCopy::new(0, 26)], 0);
+    assert!(teams.partners.is_empty());
+    teams.on_play(3, &[CardCopy::new(1, 26)], 0);
+    assert_eq!(teams.partners, vec![3]);
+    // further copies reveal nobody
+    teams.on_play(2, &[CardCopy::new(2, 26)], 0);
+    assert_eq!(teams.partners, vec![3]);
+}
+
+/// how the declarer side is formed
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum TeamFormation {
+    /// seats of the same parity as the declarer play together, only with an even number of players
+    AlternatingSeats,
+    /// the declarer calls cards, whoever plays one of them is revealed as a partner
+    CalledCard,
+    /// whoever wins the last trick joins the declarer
+    LastTrick,
+}
+
+/// the `nth` copy of a card played in this hand
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct CalledCard {
+    pub intrinsic_id: u32,
+    pub nth: u32,
+}
+
+/// partners of the declarer, only filled in once revealed
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Teams {
+    pub called_cards: Vec<CalledCard>,
+    /// copies of each called card played so far
+    played_counts: Vec<u32>,
+    pub partners: Vec<usize>,
+}
+
+impl Teams {
+    pub fn call(&mut self, called_cards: Vec<CalledCard>) {
+        self.played_counts = vec![0; called_cards.len()];
+        self.called_cards = called_cards;
+    }
+
+    /// a declarer playing the called card plays that partner's part alone
+    pub fn on_play(&mut self, seat: usize, cards: &[CardCopy], declarer: usize) {
+        for (i, called) in self.called_cards.iter().enumerate() {
+            for card in cards.iter() {
+                if card.intrinsic_id() != called.intrinsic_id {
+                    continue;
+                }
+                self.played_counts[i] += 1;
+                if self.played_counts[i] == called.nth
+                    && seat != declarer
+                    && !self.partners.contains(&seat)
+                {
+                    self.partners.push(seat);
+                }
+            }
+        }
+    }
+
+    pub fn on_last_trick(&mut self, winner: usize, declarer: usize) {
+        if winner != declarer && !self.partners.contains(&winner) {
+            self.partners.push(winner);
+        }
+    }
+}
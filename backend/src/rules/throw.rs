@@ -0,0 +1,147 @@
+use crate::model::baodatui_poker::{PowerRule, PrimeOrSub};
+use crate::model::poker::CardCopy;
+use crate::rules::combination::{level_of, Combination, Shape};
+use crate::rules::group_identical;
+use std::collections::BTreeMap;
+
+#[test]
+fn test_throw() {
+    use crate::model::poker::Suit;
+    let rule = PowerRule::new(Some(Suit::SPADES), vec![2, 3, 5]);
+    let heart = |deck: u32, num: u32| CardCopy::of(deck, Suit::HEARTS, num);
+    // ace, pair of kings and pair of 7
+    let thrown = [
+        heart(0, 1),
+        heart(0, 13),
+        heart(1, 13),
+        heart(0, 7),
+        heart(1, 7),
+    ];
+    let throw = Throw::decompose(&thrown, &rule).unwrap();
+    assert_eq!(throw.components.len(), 3);
+    let others = vec![vec![heart(2, 1), heart(2, 9), heart(3, 9)]];
+    // the pair of 9 beats the pair of 7
+    let beaten = throw.smallest_beaten(&others, &rule).unwrap();
+    assert_eq!(beaten.cards, vec![heart(0, 7), heart(1, 7)]);
+    let others = vec![vec![heart(2, 1), heart(2, 4), heart(3, 4)]];
+    assert!(throw.smallest_beaten(&others, &rule).is_none());
+}
+
+/// several combinations of the same prime or sub suit led at once,
+/// a single combination is a throw of one component
+#[derive(Debug, Clone)]
+pub struct Throw {
+    pub category: PrimeOrSub,
+    /// largest component first
+    pub components: Vec<Combination>,
+}
+
+impl Throw {
+    /// None if the cards are not of the same prime or sub suit
+    pub fn decompose(cards: &[CardCopy], rule: &PowerRule) -> Option<Self> {
+        if cards.is_empty() {
+            return None;
+        }
+        let category = rule.classify(cards[0].card());
+        if cards.iter().any(|c| rule.classify(c.card()) != category) {
+            return None;
+        }
+        let mut groups_by_width: BTreeMap<usize, Vec<Vec<CardCopy>>> = BTreeMap::new();
+        for group in group_identical(cards) {
+            groups_by_width.entry(group.len()).or_default().push(group);
+        }
+        let mut components = vec![];
+        for (width, mut groups) in groups_by_width {
+            if width == 1 {
+                for group in groups {
+                    components.push(Combination::recognize(&group, rule).unwrap());
+                }
+                continue;
+            }
+            // split tuples of the same width into maximal consecutive runs
+            groups.sort_by_key(|g| level_of(&g[0], rule));
+            let mut run: Vec<CardCopy> = vec![];
+            let mut last_level: Option<usize> = None;
+            for group in groups {
+                let level = level_of(&group[0], rule);
+                if last_level.is_some_and(|l| l + 1 != level) {
+                    components.push(Combination::recognize(&run, rule).unwrap());
+                    run.clear();
+                }
+                run.extend(group);
+                last_level = Some(level);
+            }
+            components.push(Combination::recognize(&run, rule).unwrap());
+        }
+        components.sort_by_key(|c| (std::cmp::Reverse(c.shape.card_count()), -c.rank));
+        Some(Self {
+            category,
+            components,
+        })
+    }
+
+    pub fn is_single_combination(&self) -> bool {
+        self.components.len() == 1
+    }
+
+    pub fn top(&self) -> &Combination {
+        &self.components[0]
+    }
+
+    pub fn shapes(&self) -> Vec<Shape> {
+        self.components.iter().map(|c| c.shape).collect()
+    }
+
+    /// the weakest component any of `other_hands` could beat, the thrower is forced to play it
+    pub fn smallest_beaten(
+        &self,
+        other_hands: &[Vec<CardCopy>],
+        rule: &PowerRule,
+    ) -> Option<&Combination> {
+        self.components
+            .iter()
+            .filter(|component| {
+                other_hands
+                    .iter()
+                    .any(|hand| can_beat(hand, component, rule))
+            })
+            .min_by_key(|c| (c.rank, c.shape.card_count()))
+    }
+
+    /// whether this play, made later in a trick, takes it from `best`
+    /// shapes must match, then it is decided by the top component
+    pub fn beats(&self, best: &Throw) -> bool {
+        if self.shapes() != best.shapes() {
+            return false;
+        }
+        if self.category == best.category {
+            return self.top().rank > best.top().rank;
+        }
+        self.category.is_prime() && best.category.is_sub()
+    }
+}
+
+/// whether `hand` holds a combination of the same prime or sub suit and shape ranked higher,
+/// wider tuples can stand in for narrower ones
+fn can_beat(hand: &[CardCopy], component: &Combination, rule: &PowerRule) -> bool {
+    let cards: Vec<CardCopy> = hand
+        .iter()
+        .filter(|c| rule.classify(c.card()) == component.category)
+        .cloned()
+        .collect();
+    let mut levels: Vec<(usize, i32)> = group_identical(&cards)
+        .iter()
+        .filter(|g| g.len() >= component.shape.width)
+        .map(|g| (level_of(&g[0], rule), rule.power(g[0].card())))
+        .collect();
+    levels.sort();
+    levels.dedup_by_key(|l| l.0);
+    let length = component.shape.length;
+    if levels.len() < length {
+        return false;
+    }
+    levels.windows(length).any(|w| {
+        let consecutive = w.windows(2).all(|p| p[0].0 + 1 == p[1].0);
+        consecutive && w[length - 1].1 > component.rank
+    })
+}
@@ -0,0 +1,357 @@
+use backend::global::handlers::game_handlers::{
+    BURY_BOTTOM_REQ_TYPE, CALL_PARTNER_REQ_TYPE, DEALT_CARDS_STREAM_TYPE, DECLARE_PRIME_REQ_TYPE,
+    GAME_STATE_STREAM_TYPE, PLAY_CARDS_REQ_TYPE,
+};
+use backend::global::handlers::room_handlers::{
+    CHANGE_GAME_CONFIG_REQ_TYPE, CREATE_ROOM_REQ_TYPE, ENTER_ROOM_REQ_TYPE,
+    LIST_ROOM_SIMPLE_INFO_REQ_TYPE, START_GAME_REQ_TYPE, TOGGLE_PREPARED_REQ_TYPE,
+};
+use backend::global::handlers::user_handlers::GET_CUR_USER_REQ_TYPE;
+use backend::global::settings::system_settings_arc;
+use backend::model::configs::{ConfigResult, GameConfigurations};
+use backend::model::game::{GamePhase, PlayRejection, PlayResult};
+use backend::model::game_view::{GameView, LegalMoves};
+use backend::model::room::RoomStatus;
+use backend::rules::team::{CalledCard, TeamFormation};
+use backend::test_client::Client;
+use futures::Stream;
+use futures_util::StreamExt;
+use std::pin::Pin;
+use std::time::Duration;
+use tokio::time::sleep;
+
+/// owner first, everybody prepared and the game started with `seed`
+async fn start_game(configs: &GameConfigurations, seed: u64) -> Vec<Client> {
+    let owner = Client::new_and_connect().await;
+    owner.request_no_args(CREATE_ROOM_REQ_TYPE).await.unwrap();
+    let result = owner
+        .request(CHANGE_GAME_CONFIG_REQ_TYPE, configs)
+        .await
+        .unwrap();
+    assert_eq!(result, ConfigResult::Changed);
+    let room_id = owner
+        .request_no_args(LIST_ROOM_SIMPLE_INFO_REQ_TYPE)
+        .await
+        .unwrap()[0]
+        .id;
+    let mut clients = vec![owner];
+    for _ in 1..configs.basic_configs.max_player_count {
+        let client = Client::new_and_connect_with_server(clients[0].server()).await;
+        client.request(ENTER_ROOM_REQ_TYPE, &room_id).await.unwrap();
+        clients.push(client);
+    }
+    for client in clients.iter() {
+        client
+            .request_no_args(TOGGLE_PREPARED_REQ_TYPE)
+            .await
+            .unwrap();
+    }
+    clients[0]
+        .request(START_GAME_REQ_TYPE, &Some(seed))
+        .await
+        .unwrap();
+    clients
+}
+
+/// the first view of the stream in `phase`
+async fn view_in(stream: &mut Pin<Box<dyn Stream<Item = GameView>>>, phase: GamePhase) -> GameView {
+    loop {
+        let view = stream.next().await.unwrap();
+        if view.phase == phase {
+            return view;
+        }
+    }
+}
+
+#[tokio::test]
+async fn in_game_room_not_removed_test() {
+    system_settings_arc().write().non_active_room_time = 300;
+    let mut configs = GameConfigurations::default();
+    configs.basic_configs.max_player_count = 4;
+    let clients = start_game(&configs, 42).await;
+    // still dealing, nothing about the room changes meanwhile
+    sleep(Duration::from_millis(1000)).await;
+    let list = clients[0]
+        .request_no_args(LIST_ROOM_SIMPLE_INFO_REQ_TYPE)
+        .await
+        .unwrap();
+    assert_eq!(list.len(), 1);
+    assert!(matches!(list[0].status, RoomStatus::InGame));
+}
+
+#[tokio::test]
+async fn game_state_stream_test() {
+    let mut configs = GameConfigurations::default();
+    configs.basic_configs.max_player_count = 4;
+    configs.time_configs.deal_interval_millis = 1;
+    let clients = start_game(&configs, 42).await;
+    let mut state_stream = clients[1]
+        .stream_no_args(GAME_STATE_STREAM_TYPE)
+        .await
+        .unwrap();
+    let mut view = state_stream.next().await.unwrap();
+    assert_eq!(view.seat, Some(1));
+    while view.phase == GamePhase::Dealing {
+        view = state_stream.next().await.unwrap();
+    }
+    // 216 cards, 4 players and a bottom of 8
+    assert_eq!(view.hand.len(), 52);
+    assert!(view.players.iter().all(|p| p.card_count == 52));
+    assert!(view.bottom.is_none());
+    clients[0].shutdown_and_wait_server_exit().await;
+}
+
+#[tokio::test]
+async fn game_state_stream_across_hands_test() {
+    let mut configs = GameConfigurations::default();
+    configs.basic_configs.max_player_count = 4;
+    configs.match_configs.max_hands = Some(2);
+    let time = &mut configs.time_configs;
+    time.deal_interval_millis = 1;
+    time.declare_prime_secs = 0;
+    time.bury_bottom_secs = 0;
+    time.call_partner_secs = 0;
+    time.play_turn_secs = 0;
+    time.reserve_secs = Some(0);
+    let clients = start_game(&configs, 42).await;
+    let mut state_stream = clients[1]
+        .stream_no_args(GAME_STATE_STREAM_TYPE)
+        .await
+        .unwrap();
+    // every hand is played out by timeouts, the stream ends with the match
+    let mut finished_hands = 0;
+    let mut last_phase = None;
+    let all_views = async {
+        while let Some(view) = state_stream.next().await {
+            if view.phase == GamePhase::Finished && last_phase != Some(GamePhase::Finished) {
+                finished_hands += 1;
+            }
+            last_phase = Some(view.phase);
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(60), all_views)
+        .await
+        .unwrap();
+    assert_eq!(finished_hands, 2);
+}
+
+#[tokio::test]
+async fn play_cards_rejection_test() {
+    let mut configs = GameConfigurations::default();
+    configs.basic_configs.max_player_count = 4;
+    let clients = start_game(&configs, 42).await;
+    let mut state_stream = clients[0]
+        .stream_no_args(GAME_STATE_STREAM_TYPE)
+        .await
+        .unwrap();
+    let view = state_stream.next().await.unwrap();
+    let result = clients[0]
+        .request(PLAY_CARDS_REQ_TYPE, &view.hand[..1].to_vec())
+        .await
+        .unwrap();
+    assert_eq!(result, PlayResult::Rejected(PlayRejection::NotPlayingPhase));
+    let outsider = Client::new_and_connect_with_server(clients[0].server()).await;
+    let result = outsider
+        .request(PLAY_CARDS_REQ_TYPE, &vec![])
+        .await
+        .unwrap();
+    assert_eq!(result, PlayResult::Rejected(PlayRejection::NotPlaying));
+    clients[0].shutdown_and_wait_server_exit().await;
+}
+
+#[tokio::test]
+async fn reconnect_test() {
+    let mut configs = GameConfigurations::default();
+    configs.basic_configs.max_player_count = 4;
+    configs.time_configs.deal_interval_millis = 0;
+    configs.time_configs.declare_prime_secs = 60;
+    let mut clients = start_game(&configs, 42).await;
+    let server = clients[0].server();
+    let user = clients[1]
+        .request_no_args(GET_CUR_USER_REQ_TYPE)
+        .await
+        .unwrap();
+    let mut state_stream = clients[1]
+        .stream_no_args(GAME_STATE_STREAM_TYPE)
+        .await
+        .unwrap();
+    let mut view = state_stream.next().await.unwrap();
+    while view.phase == GamePhase::Dealing {
+        view = state_stream.next().await.unwrap();
+    }
+    drop(state_stream);
+    clients.remove(1).shutdown_client();
+
+    // others see the player go offline
+    let mut owner_stream = clients[0]
+        .stream_no_args(GAME_STATE_STREAM_TYPE)
+        .await
+        .unwrap();
+    while owner_stream.next().await.unwrap().players[1].online {}
+
+    let mut back = Client::new_with_server(server);
+    back.connect_with_uuid(user.uuid.as_str()).await;
+    let mut state_stream = back.stream_no_args(GAME_STATE_STREAM_TYPE).await.unwrap();
+    let snapshot = state_stream.next().await.unwrap();
+    assert_eq!(snapshot.seat, Some(1));
+    assert_eq!(snapshot.hand, view.hand);
+    assert!(snapshot.players[1].online);
+    back.shutdown_and_wait_server_exit().await;
+}
+
+#[tokio::test]
+async fn declare_prime_test() {
+    let mut configs = GameConfigurations::default();
+    configs.basic_configs.max_player_count = 4;
+    configs.time_configs.deal_interval_millis = 1;
+    configs.time_configs.declare_prime_secs = 60;
+    let clients = start_game(&configs, 42).await;
+    let mut streams = vec![];
+    for client in clients.iter() {
+        streams.push(client.stream_no_args(GAME_STATE_STREAM_TYPE).await.unwrap());
+    }
+    let mut declared = None;
+    for (seat, stream) in streams.iter_mut().enumerate() {
+        let view = view_in(stream, GamePhase::DeclaringPrime).await;
+        if let LegalMoves::DeclarePrime { candidates } = view.legal_moves {
+            if declared.is_none() && !candidates.is_empty() {
+                declared = Some((seat, candidates[0].clone()));
+            }
+        }
+    }
+    let (seat, cards) = declared.unwrap();
+    clients[seat]
+        .request(DECLARE_PRIME_REQ_TYPE, &cards)
+        .await
+        .unwrap();
+    // the same cards cannot override themselves
+    assert!(clients[seat]
+        .request(DECLARE_PRIME_REQ_TYPE, &cards)
+        .await
+        .is_err());
+    let view = loop {
+        let view = streams[0].next().await.unwrap();
+        if view.declaration.is_some() {
+            break view;
+        }
+    };
+    let declaration = view.declaration.unwrap();
+    assert_eq!(declaration.seat, seat);
+    assert_eq!(declaration.cards, cards);
+    clients[0].shutdown_and_wait_server_exit().await;
+}
+
+#[tokio::test]
+async fn bury_bottom_test() {
+    let mut configs = GameConfigurations::default();
+    configs.basic_configs.max_player_count = 4;
+    configs.time_configs.deal_interval_millis = 1;
+    configs.time_configs.declare_prime_secs = 0;
+    configs.time_configs.bury_bottom_secs = 60;
+    let clients = start_game(&configs, 42).await;
+    let mut stream = clients[0]
+        .stream_no_args(GAME_STATE_STREAM_TYPE)
+        .await
+        .unwrap();
+    let declarer = view_in(&mut stream, GamePhase::ExchangingBottom)
+        .await
+        .declarer;
+    let mut stream = clients[declarer]
+        .stream_no_args(GAME_STATE_STREAM_TYPE)
+        .await
+        .unwrap();
+    let view = view_in(&mut stream, GamePhase::ExchangingBottom).await;
+    let LegalMoves::BuryBottom { count } = view.legal_moves else {
+        panic!("declarer should bury, got {:?}", view.legal_moves);
+    };
+    // the last cards of the hand, not what auto play would bury
+    let buried = view.hand[view.hand.len() - count..].to_vec();
+    let other = (declarer + 1) % 4;
+    assert!(clients[other]
+        .request(BURY_BOTTOM_REQ_TYPE, &buried)
+        .await
+        .is_err());
+    clients[declarer]
+        .request(BURY_BOTTOM_REQ_TYPE, &buried)
+        .await
+        .unwrap();
+    let view = view_in(&mut stream, GamePhase::Playing).await;
+    assert_eq!(view.bottom, Some(buried.clone()));
+    assert!(buried.iter().all(|c| !view.hand.contains(c)));
+    clients[0].shutdown_and_wait_server_exit().await;
+}
+
+#[tokio::test]
+async fn call_partner_test() {
+    let mut configs = GameConfigurations::default();
+    configs.basic_configs.max_player_count = 4;
+    configs.play_configs.team_formation = TeamFormation::CalledCard;
+    configs.time_configs.deal_interval_millis = 1;
+    configs.time_configs.declare_prime_secs = 0;
+    configs.time_configs.bury_bottom_secs = 60;
+    configs.time_configs.call_partner_secs = 60;
+    let clients = start_game(&configs, 42).await;
+    let mut stream = clients[0]
+        .stream_no_args(GAME_STATE_STREAM_TYPE)
+        .await
+        .unwrap();
+    let view = view_in(&mut stream, GamePhase::ExchangingBottom).await;
+    let declarer = view.declarer;
+    let mut stream = clients[declarer]
+        .stream_no_args(GAME_STATE_STREAM_TYPE)
+        .await
+        .unwrap();
+    let view = view_in(&mut stream, GamePhase::ExchangingBottom).await;
+    let buried = view.hand[..view.bottom_size].to_vec();
+    clients[declarer]
+        .request(BURY_BOTTOM_REQ_TYPE, &buried)
+        .await
+        .unwrap();
+    let view = view_in(&mut stream, GamePhase::CallingPartner).await;
+    assert!(matches!(
+        view.legal_moves,
+        LegalMoves::CallPartner { count: 1 }
+    ));
+    // the second heart ace played
+    let called = vec![CalledCard {
+        intrinsic_id: 26,
+        nth: 2,
+    }];
+    let other = (declarer + 1) % 4;
+    assert!(clients[other]
+        .request(CALL_PARTNER_REQ_TYPE, &called)
+        .await
+        .is_err());
+    clients[declarer]
+        .request(CALL_PARTNER_REQ_TYPE, &called)
+        .await
+        .unwrap();
+    let view = view_in(&mut stream, GamePhase::Playing).await;
+    assert_eq!(view.called_cards, called);
+    clients[0].shutdown_and_wait_server_exit().await;
+}
+
+#[tokio::test]
+async fn dealt_cards_stream_without_pause_test() {
+    let mut configs = GameConfigurations::default();
+    configs.basic_configs.max_player_count = 4;
+    configs.time_configs.deal_interval_millis = 0;
+    configs.time_configs.declare_prime_secs = 60;
+    let clients = start_game(&configs, 42).await;
+    let dealt_stream = clients[1]
+        .stream_no_args(DEALT_CARDS_STREAM_TYPE)
+        .await
+        .unwrap();
+    let mut dealt: Vec<_> = dealt_stream.collect::<Vec<_>>().await.concat();
+    // 216 cards, 4 players and a bottom of 8, none missed and none twice
+    assert_eq!(dealt.len(), 52);
+    let mut stream = clients[1]
+        .stream_no_args(GAME_STATE_STREAM_TYPE)
+        .await
+        .unwrap();
+    let mut hand = view_in(&mut stream, GamePhase::DeclaringPrime).await.hand;
+    hand.sort_by_key(|c| c.id);
+    dealt.sort_by_key(|c| c.id);
+    assert_eq!(dealt, hand);
+    clients[0].shutdown_and_wait_server_exit().await;
+}
@@ -1,10 +1,17 @@
 use backend::global::handlers::room_handlers::{
     ALL_ROOM_SIMPLE_INFO_STREAM_TYPE, CHANGE_GAME_CONFIG_REQ_TYPE, CREATE_ROOM_REQ_TYPE,
     ENTER_ROOM_REQ_TYPE, LEAVE_ROOM_REQ_TYPE, LIST_ROOM_SIMPLE_INFO_REQ_TYPE,
-    ROOM_DETAILED_INFO_STREAM_TYPE,
+    ROOM_DETAILED_INFO_STREAM_TYPE, SPECTATE_ROOM_REQ_TYPE, START_GAME_REQ_TYPE,
+    TAKE_SEAT_REQ_TYPE, TOGGLE_PREPARED_REQ_TYPE,
+};
+use backend::global::handlers::user_handlers::{
+    APPLY_PRESET_REQ_TYPE, GET_CUR_USER_REQ_TYPE, LIST_PRESETS_REQ_TYPE, SAVE_PRESET_REQ_TYPE,
 };
 use backend::global::settings::system_settings_arc;
-use backend::model::configs::GameConfigurations;
+use backend::model::configs::{ConfigResult, GameConfigurations};
+use backend::model::preset::{ConfigPreset, BEGINNER_PRESET};
+use backend::model::room::{RoomSimpleInfo, RoomStatus};
+use backend::rules::team::TeamFormation;
 use backend::test_client::Client;
 use futures_util::StreamExt;
 use std::time::Duration;
@@ -74,10 +81,126 @@ async fn change_game_config_test() {
     client.request_no_args(CREATE_ROOM_REQ_TYPE).await.unwrap();
     let mut new_config = GameConfigurations::default();
     new_config.basic_configs.max_player_count = 4;
-    client
+    let result = client
         .request(CHANGE_GAME_CONFIG_REQ_TYPE, &new_config)
         .await
         .unwrap();
+    assert_eq!(result, ConfigResult::Changed);
+    client.shutdown_and_wait_server_exit().await;
+}
+
+/// fields reported invalid, empty once the change is accepted
+async fn invalid_fields(client: &Client, configs: &GameConfigurations) -> Vec<String> {
+    match client
+        .request(CHANGE_GAME_CONFIG_REQ_TYPE, configs)
+        .await
+        .unwrap()
+    {
+        ConfigResult::Changed => vec![],
+        ConfigResult::Invalid(errors) => errors.into_iter().map(|e| e.field).collect(),
+    }
+}
+
+#[tokio::test]
+async fn change_invalid_game_config_test() {
+    let client = Client::new_and_connect().await;
+    client.request_no_args(CREATE_ROOM_REQ_TYPE).await.unwrap();
+    let room_id = client
+        .request_no_args(LIST_ROOM_SIMPLE_INFO_REQ_TYPE)
+        .await
+        .unwrap()[0]
+        .id;
+    let mut new_config = GameConfigurations::default();
+    new_config.basic_configs.max_player_count = 1;
+    new_config.score_configs.level_step = 0;
+    assert_eq!(
+        invalid_fields(&client, &new_config).await,
+        ["basic_configs.max_player_count", "score_configs.level_step"]
+    );
+    new_config.score_configs.level_step = GameConfigurations::default().score_configs.level_step;
+    let others = [
+        Client::new_and_connect_with_server(client.server()).await,
+        Client::new_and_connect_with_server(client.server()).await,
+    ];
+    for other in others.iter() {
+        other.request(ENTER_ROOM_REQ_TYPE, &room_id).await.unwrap();
+    }
+    // 3 users are seated already
+    new_config.basic_configs.max_player_count = 2;
+    assert_eq!(
+        invalid_fields(&client, &new_config).await,
+        ["basic_configs.max_player_count"]
+    );
+    // 3 cannot alternate seats
+    new_config.basic_configs.max_player_count = 3;
+    assert_eq!(
+        invalid_fields(&client, &new_config).await,
+        ["play_configs.team_formation"]
+    );
+    new_config.play_configs.team_formation = TeamFormation::CalledCard;
+    assert!(invalid_fields(&client, &new_config).await.is_empty());
+    client.shutdown_and_wait_server_exit().await;
+}
+
+#[tokio::test]
+async fn room_from_preferred_preset_test() {
+    let client = Client::new_and_connect().await;
+    let mut configs = GameConfigurations::default();
+    configs.basic_configs.max_player_count = 1;
+    let mut preset = ConfigPreset {
+        name: "mine".to_string(),
+        configs,
+        built_in: false,
+    };
+    assert!(client.request(SAVE_PRESET_REQ_TYPE, &preset).await.is_err());
+    preset.configs.basic_configs.max_player_count = 8;
+    client.request(SAVE_PRESET_REQ_TYPE, &preset).await.unwrap();
+    let presets = client.request_no_args(LIST_PRESETS_REQ_TYPE).await.unwrap();
+    assert_eq!(presets.last().unwrap().name, "mine");
+    client
+        .request(APPLY_PRESET_REQ_TYPE, &BEGINNER_PRESET.to_string())
+        .await
+        .unwrap();
+    client.request_no_args(CREATE_ROOM_REQ_TYPE).await.unwrap();
+    let max_user_count = |list: Vec<RoomSimpleInfo>| list[0].max_user_count;
+    let list = client
+        .request_no_args(LIST_ROOM_SIMPLE_INFO_REQ_TYPE)
+        .await
+        .unwrap();
+    assert_eq!(max_user_count(list), 4);
+    // the owned room changes along
+    client
+        .request(APPLY_PRESET_REQ_TYPE, &"mine".to_string())
+        .await
+        .unwrap();
+    let list = client
+        .request_no_args(LIST_ROOM_SIMPLE_INFO_REQ_TYPE)
+        .await
+        .unwrap();
+    assert_eq!(max_user_count(list), 8);
+    // 3 seated users do not fit a preset for 2, which is not preferred then
+    preset.name = "duo".to_string();
+    preset.configs.basic_configs.max_player_count = 2;
+    client.request(SAVE_PRESET_REQ_TYPE, &preset).await.unwrap();
+    let room_id = client
+        .request_no_args(LIST_ROOM_SIMPLE_INFO_REQ_TYPE)
+        .await
+        .unwrap()[0]
+        .id;
+    let others = [
+        Client::new_and_connect_with_server(client.server()).await,
+        Client::new_and_connect_with_server(client.server()).await,
+    ];
+    for other in others.iter() {
+        other.request(ENTER_ROOM_REQ_TYPE, &room_id).await.unwrap();
+    }
+    assert!(client
+        .request(APPLY_PRESET_REQ_TYPE, &"duo".to_string())
+        .await
+        .is_err());
+    let user = client.request_no_args(GET_CUR_USER_REQ_TYPE).await.unwrap();
+    let preferred = user.preferred_game_config.unwrap();
+    assert_eq!(preferred.basic_configs.max_player_count, 8);
     client.shutdown_and_wait_server_exit().await;
 }
 
@@ -206,3 +329,114 @@ async fn room_detailed_info_stream_by_user_leave_test() {}
 
 #[tokio::test]
 async fn room_detailed_info_stream_when_room_removed_test() {}
+
+#[tokio::test]
+async fn prepare_and_start_game_test() {
+    let client = Client::new_and_connect().await;
+    client.request_no_args(CREATE_ROOM_REQ_TYPE).await.unwrap();
+    let mut new_config = GameConfigurations::default();
+    new_config.basic_configs.max_player_count = 4;
+    client
+        .request(CHANGE_GAME_CONFIG_REQ_TYPE, &new_config)
+        .await
+        .unwrap();
+    let room_id = client
+        .request_no_args(LIST_ROOM_SIMPLE_INFO_REQ_TYPE)
+        .await
+        .unwrap()[0]
+        .id;
+    let mut others = vec![];
+    for _ in 0..3 {
+        let other = Client::new_and_connect_with_server(client.server()).await;
+        other.request(ENTER_ROOM_REQ_TYPE, &room_id).await.unwrap();
+        others.push(other);
+    }
+    assert!(client.request(START_GAME_REQ_TYPE, &None).await.is_err());
+    assert!(client
+        .request_no_args(TOGGLE_PREPARED_REQ_TYPE)
+        .await
+        .unwrap());
+    for other in others.iter() {
+        assert!(other
+            .request_no_args(TOGGLE_PREPARED_REQ_TYPE)
+            .await
+            .unwrap());
+    }
+    // only the owner can start
+    assert!(others[0].request(START_GAME_REQ_TYPE, &None).await.is_err());
+    client
+        .request(START_GAME_REQ_TYPE, &Some(42))
+        .await
+        .unwrap();
+    let list = client
+        .request_no_args(LIST_ROOM_SIMPLE_INFO_REQ_TYPE)
+        .await
+        .unwrap();
+    assert!(matches!(list[0].status, RoomStatus::InGame));
+    assert!(client
+        .request_no_args(TOGGLE_PREPARED_REQ_TYPE)
+        .await
+        .is_err());
+    // a seat left during the game is not entered, only spectated
+    others[0]
+        .request_no_args(LEAVE_ROOM_REQ_TYPE)
+        .await
+        .unwrap();
+    let latecomer = Client::new_and_connect_with_server(client.server()).await;
+    assert!(latecomer
+        .request(ENTER_ROOM_REQ_TYPE, &room_id)
+        .await
+        .is_err());
+    latecomer
+        .request(SPECTATE_ROOM_REQ_TYPE, &room_id)
+        .await
+        .unwrap();
+    client.shutdown_and_wait_server_exit().await;
+}
+
+#[tokio::test]
+async fn spectate_and_take_seat_test() {
+    let client = Client::new_and_connect().await;
+    client.request_no_args(CREATE_ROOM_REQ_TYPE).await.unwrap();
+    let mut new_config = GameConfigurations::default();
+    new_config.basic_configs.max_player_count = 2;
+    client
+        .request(CHANGE_GAME_CONFIG_REQ_TYPE, &new_config)
+        .await
+        .unwrap();
+    let room_id = client
+        .request_no_args(LIST_ROOM_SIMPLE_INFO_REQ_TYPE)
+        .await
+        .unwrap()[0]
+        .id;
+    let spectator = Client::new_and_connect_with_server(client.server()).await;
+    spectator
+        .request(SPECTATE_ROOM_REQ_TYPE, &room_id)
+        .await
+        .unwrap();
+    let list = client
+        .request_no_args(LIST_ROOM_SIMPLE_INFO_REQ_TYPE)
+        .await
+        .unwrap();
+    assert_eq!(list[0].cur_user_count, 1);
+    assert!(spectator
+        .request_no_args(TOGGLE_PREPARED_REQ_TYPE)
+        .await
+        .is_err());
+    let mut detail_stream = spectator
+        .stream_no_args(ROOM_DETAILED_INFO_STREAM_TYPE)
+        .await
+        .unwrap();
+    while detail_stream.next().await.unwrap().spectator_infos.len() != 1 {}
+    spectator.request_no_args(TAKE_SEAT_REQ_TYPE).await.unwrap();
+    loop {
+        let detail = detail_stream.next().await.unwrap();
+        if detail.user_in_room_infos.len() == 2 {
+            assert!(detail.spectator_infos.is_empty());
+            break;
+        }
+    }
+    // the spectator already took the seat
+    assert!(spectator.request_no_args(TAKE_SEAT_REQ_TYPE).await.is_err());
+    client.shutdown_and_wait_server_exit().await;
+}